@@ -10,7 +10,9 @@ use crate::impl_cl_type_trait;
 use mesa_rust::compiler::clc::*;
 use mesa_rust::compiler::nir::*;
 use mesa_rust::nir_pass;
+use mesa_rust::pipe::context::MapFlags;
 use mesa_rust::pipe::context::RWFlags;
+use mesa_rust::pipe::context::ResourceMapType;
 use mesa_rust::pipe::resource::*;
 use mesa_rust::pipe::screen::ResourceType;
 use mesa_rust_gen::*;
@@ -20,15 +22,21 @@ use rusticl_opencl_gen::*;
 use spirv::SpirvKernelInfo;
 
 use std::cmp;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::mem::size_of;
 use std::os::raw::c_void;
 use std::ptr;
 use std::slice;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::thread;
+use std::time::Duration;
 
 // ugh, we are not allowed to take refs, so...
 #[derive(Clone)]
@@ -54,6 +62,19 @@ pub enum KernelArgType {
     MemLocal = 7,
 }
 
+/// Resources a kernel may access indirectly, as declared via `clSetKernelExecInfo`'s
+/// `CL_KERNEL_EXEC_INFO_SVM_PTRS`/`CL_KERNEL_EXEC_INFO_INDIRECT_*_ACCESS`. These aren't passed as
+/// explicit kernel arguments, so `Kernel::launch` has to separately keep them resident.
+#[derive(Clone)]
+enum IndirectMem {
+    /// The allocations the pointers passed to `CL_KERNEL_EXEC_INFO_SVM_PTRS` (or the buffer
+    /// variant) resolved to.
+    Specific(Vec<Arc<Buffer>>),
+    /// Every SVM/coarse-grained allocation currently live in the kernel's context, for the
+    /// `INDIRECT_*_ACCESS` flags that tell us to assume anything might be reachable.
+    All,
+}
+
 #[derive(Hash, PartialEq, Eq, Clone)]
 enum InternalKernelArgType {
     ConstantBuffer,
@@ -289,6 +310,15 @@ pub struct KernelInfo {
     work_group_size: [usize; 3],
     subgroup_size: usize,
     num_subgroups: usize,
+    /// Compiler-computed scratch/private memory footprint in bytes, captured once the final
+    /// `nir_lower_vars_to_explicit_types`/DCE passes in `lower_and_optimize_nir` have settled
+    /// which private variables actually survive. Doesn't include backend register spills; see
+    /// [`Kernel::priv_mem_size`] for the full `CL_KERNEL_PRIVATE_MEM_SIZE` picture.
+    priv_mem_size: u32,
+    /// Compile-time (`static`) shared/local memory footprint in bytes. Doesn't include `__local`
+    /// kernel arguments, whose size is only known once set via `clSetKernelArg`; see
+    /// [`Kernel::local_mem_size`] for the full `CL_KERNEL_LOCAL_MEM_SIZE` picture.
+    local_mem_size: u32,
 }
 
 struct CSOWrapper {
@@ -330,7 +360,15 @@ pub struct NirKernelBuild {
     info: pipe_compute_state_object_info,
     shared_size: u64,
     printf_info: Option<NirPrintfInfo>,
+    /// True if `printf`s in this build were lowered to write a base-relative identifier
+    /// instead of a format-string pointer into the printf buffer. The format strings stay
+    /// host-side in `printf_info` and are looked up by identifier on decode.
+    printf_uses_base_identifier: bool,
     internal_args: Vec<InternalKernelArg>,
+    /// Pre-CSO NIR kept around to re-specialize for a concrete local work-group size. Only
+    /// populated when the device opts into specialization and the build still has a variable
+    /// work-group size worth specializing.
+    source_nir: Option<NirShader>,
 }
 
 // SAFETY: `CSOWrapper` is only safe to use if the device supports `PIPE_CAP_SHAREABLE_SHADERS` and
@@ -343,7 +381,11 @@ impl NirKernelBuild {
         dev: &'static Device,
         mut nir: NirShader,
         internal_args: Vec<InternalKernelArg>,
+        printf_uses_base_identifier: bool,
     ) -> Self {
+        let source_nir = (dev.specializes_kernels() && nir.workgroup_size() == [0; 3])
+            .then(|| nir.clone_shader());
+
         let cso = CSOWrapper::new(dev, &nir);
         let info = cso.get_cso_info();
         let cb = Self::create_nir_constant_buffer(dev, &nir);
@@ -362,10 +404,30 @@ impl NirKernelBuild {
             info: info,
             shared_size: shared_size,
             printf_info: printf_info,
+            printf_uses_base_identifier: printf_uses_base_identifier,
             internal_args: internal_args,
+            source_nir: source_nir,
         }
     }
 
+    /// Re-specializes this build for a concrete `block` work-group size, baking the dimensions
+    /// in and re-running optimizations with unrolling enabled so loop bounds derived from
+    /// `get_local_size`/`num_workgroups` fully resolve. Returns `None` when no source NIR was
+    /// retained, i.e. the device didn't opt into specialization or the build already has a fixed
+    /// work-group size; callers should keep using the generic build in that case.
+    fn specialize_for_block(&self, dev: &'static Device, block: [u32; 3]) -> Option<Self> {
+        let mut nir = self.source_nir.as_ref()?.clone_shader();
+        nir.set_workgroup_size(block);
+        opt_nir_with_unroll(&mut nir, dev, true, true);
+
+        Some(NirKernelBuild::new(
+            dev,
+            nir,
+            self.internal_args.clone(),
+            self.printf_uses_base_identifier,
+        ))
+    }
+
     fn create_nir_constant_buffer(dev: &Device, nir: &NirShader) -> Option<Arc<PipeResource>> {
         let buf = nir.get_constant_buffer();
         let len = buf.len() as u32;
@@ -378,7 +440,15 @@ impl NirKernelBuild {
                 .unwrap();
 
             dev.helper_ctx()
-                .exec(|ctx| ctx.buffer_subdata(&res, 0, buf.as_ptr().cast(), len))
+                .exec(|ctx| {
+                    ctx.buffer_subdata(
+                        &res,
+                        0,
+                        buf.as_ptr().cast(),
+                        len,
+                        MapFlags::new(RWFlags::WR).discard_whole_resource(),
+                    )
+                })
                 .wait();
 
             Some(Arc::new(res))
@@ -388,13 +458,29 @@ impl NirKernelBuild {
     }
 }
 
+/// A `clSetProgramSpecializationConstant`-style snapshot: the value each `OpSpecConstant`'s
+/// `spec_id` was overridden with at the point a program (and, transitively, this kernel) was
+/// built. Kept as a `BTreeMap` rather than a `HashMap` so it has a stable `Hash`/`Eq`, letting it
+/// key build caches.
+pub type SpecConstants = BTreeMap<u32, Vec<u8>>;
+
 pub struct Kernel {
     pub base: CLObjectBase<CL_INVALID_KERNEL>,
     pub prog: Arc<Program>,
     pub name: String,
     values: Mutex<Vec<Option<KernelArgValue>>>,
     builds: HashMap<&'static Device, Arc<NirKernelBuild>>,
+    /// Builds specialized for a concrete local work-group size, keyed by device and the block
+    /// dimensions they were specialized for. Populated lazily from `builds` on enqueue.
+    specialized_builds: Mutex<HashMap<(&'static Device, [u32; 3]), Arc<NirKernelBuild>>>,
     pub kernel_info: Arc<KernelInfo>,
+    /// The `spec_constants` the program was built with when `builds`/`kernel_info` above were
+    /// produced. `OpSpecConstant`s fold into NIR as part of `spirv_to_nir`, so a different set of
+    /// values means a different compiled `builds`/`kernel_info` -- this snapshot is what a clone
+    /// carries forward so it keeps matching the NIR it was actually specialized against.
+    spec_constants: SpecConstants,
+    /// Set by `clSetKernelExecInfo`; `None` if the application never called it for this kernel.
+    indirect_mem: Mutex<Option<IndirectMem>>,
 }
 
 impl_cl_type_trait!(cl_kernel, Kernel, CL_INVALID_KERNEL);
@@ -413,6 +499,18 @@ where
 }
 
 fn opt_nir(nir: &mut NirShader, dev: &Device, has_explicit_types: bool) {
+    opt_nir_with_unroll(nir, dev, has_explicit_types, false)
+}
+
+/// Same as [`opt_nir`], but `force_unroll` additionally enables loop unrolling even when the
+/// driver wouldn't otherwise ask for it. Used when specializing a build for a concrete
+/// work-group size, where baked-in loop bounds are newly unrollable.
+fn opt_nir_with_unroll(
+    nir: &mut NirShader,
+    dev: &Device,
+    has_explicit_types: bool,
+    force_unroll: bool,
+) {
     let nir_options = unsafe {
         &*dev
             .screen
@@ -465,7 +563,7 @@ fn opt_nir(nir: &mut NirShader, dev: &Device, has_explicit_types: bool) {
             nir_variable_mode::nir_var_mem_generic | nir_variable_mode::nir_var_uniform,
         );
 
-        if nir_options.max_unroll_iterations != 0 {
+        if nir_options.max_unroll_iterations != 0 || force_unroll {
             progress |= nir_pass!(nir, nir_opt_loop_unroll);
         }
         nir.sweep_mem();
@@ -488,12 +586,47 @@ unsafe extern "C" fn can_remove_var(var: *mut nir_variable, _: *mut c_void) -> b
     }
 }
 
+/// # Safety
+///
+/// Called back by `nir_lower_mem_access_bit_sizes` with the `cb_data` set up in
+/// `lower_and_optimize_nir`, which must point to a `bool` telling whether 64-bit accesses have
+/// to be split into 32-bit ones.
+unsafe extern "C" fn sub32_mem_access_size_align(
+    _intrin: nir_intrinsic_op,
+    bytes: u8,
+    bit_size: u8,
+    align_mul: u32,
+    align_offset: u32,
+    _offset_is_const: bool,
+    cb_data: *const c_void,
+) -> nir_mem_access_size_align {
+    // SAFETY: the caller passes a pointer to a live `bool`, see above
+    let split_64bit = unsafe { *cb_data.cast::<bool>() };
+    // accesses smaller than 32 bits are widened, 64-bit ones are split into 32-bit words when
+    // requested; everything else is already native
+    let bit_size = if bit_size == 64 && split_64bit {
+        32
+    } else {
+        cmp::max(bit_size, 32)
+    };
+    let num_components = cmp::max(1, (bytes as u32 * 8 / bit_size) as u8);
+    // cap the alignment at one 32-bit word
+    let align = cmp::min(align_mul, 4);
+    let _ = align_offset;
+
+    nir_mem_access_size_align {
+        bit_size: bit_size,
+        num_components: num_components,
+        align: align,
+    }
+}
+
 fn lower_and_optimize_nir(
     dev: &Device,
     nir: &mut NirShader,
     args: &[spirv::SPIRVKernelArg],
     lib_clc: &NirShader,
-) -> (Vec<KernelArg>, Vec<InternalKernelArg>) {
+) -> (Vec<KernelArg>, Vec<InternalKernelArg>, bool) {
     let address_bits_ptr_type;
     let address_bits_base_type;
     let global_address_format;
@@ -550,9 +683,13 @@ fn lower_and_optimize_nir(
 
     nir_pass!(nir, nir_dedup_inline_samplers);
 
+    // Using a base identifier keeps the format strings host-side in `NirPrintfInfo` and only
+    // writes a small integer plus the argument bytes into the device printf buffer, so gate it
+    // behind a driver flag rather than always shipping the full string data to the device.
+    let printf_uses_base_identifier = dev.printf_supports_base_identifier();
     let printf_opts = nir_lower_printf_options {
         ptr_bit_size: 0,
-        use_printf_base_identifier: false,
+        use_printf_base_identifier: printf_uses_base_identifier,
         max_buffer_size: dev.printf_buffer_size() as u32,
     };
     nir_pass!(nir, nir_lower_printf, &printf_opts);
@@ -795,6 +932,20 @@ fn lower_and_optimize_nir(
         global_address_format,
     );
 
+    // Some backends can only issue aligned 32-bit global/constant loads and stores. Narrow
+    // (and, on 32-bit-address devices, 64-bit) accesses get rewritten into aligned 32-bit
+    // accesses with the real components extracted/packed on either side.
+    if dev.requires_32bit_mem_access() {
+        let split_64bit = dev.address_bits() == 32;
+        let mem_access_opts = nir_lower_mem_access_bit_sizes_options {
+            callback: Some(sub32_mem_access_size_align),
+            cb_data: (&split_64bit as *const bool).cast(),
+            modes: nir_variable_mode::nir_var_mem_global | nir_variable_mode::nir_var_mem_constant,
+            may_lower_unaligned_stores_to_atomics: false,
+        };
+        nir_pass!(nir, nir_lower_mem_access_bit_sizes, &mem_access_opts);
+    }
+
     nir_pass!(nir, rusticl_lower_intrinsics, &mut lower_state);
     nir_pass!(
         nir,
@@ -832,7 +983,7 @@ fn lower_and_optimize_nir(
     nir_pass!(nir, nir_opt_dce);
     nir.sweep_mem();
 
-    (args, internal_args)
+    (args, internal_args, printf_uses_base_identifier)
 }
 
 pub struct SPIRVToNirResult {
@@ -847,6 +998,7 @@ impl SPIRVToNirResult {
         args: Vec<KernelArg>,
         internal_args: Vec<InternalKernelArg>,
         nir: NirShader,
+        printf_uses_base_identifier: bool,
     ) -> Self {
         let wgs = nir.workgroup_size();
         let kernel_info = KernelInfo {
@@ -855,11 +1007,37 @@ impl SPIRVToNirResult {
             work_group_size: [wgs[0] as usize, wgs[1] as usize, wgs[2] as usize],
             subgroup_size: nir.subgroup_size() as usize,
             num_subgroups: nir.num_subgroups() as usize,
+            priv_mem_size: nir.scratch_size(),
+            local_mem_size: nir.shared_size(),
         };
 
         Self {
             kernel_info: kernel_info,
-            nir_kernel_build: NirKernelBuild::new(dev, nir, internal_args),
+            nir_kernel_build: NirKernelBuild::new(
+                dev,
+                nir,
+                internal_args,
+                printf_uses_base_identifier,
+            ),
+        }
+    }
+
+    fn write_cache_str(blob: &mut blob, s: &str) {
+        unsafe {
+            blob_write_uint32(blob, s.len() as u32);
+            blob_write_bytes(blob, s.as_ptr().cast(), s.len());
+        }
+    }
+
+    fn read_cache_str(reader: &mut blob_reader) -> Option<String> {
+        unsafe {
+            let len = blob_read_uint32(reader) as usize;
+            let ptr = blob_read_bytes(reader, len);
+            if reader.overrun {
+                return None;
+            }
+            let bytes = slice::from_raw_parts(ptr.cast(), len);
+            String::from_utf8(bytes.to_vec()).ok()
         }
     }
 
@@ -869,6 +1047,20 @@ impl SPIRVToNirResult {
             blob_reader_init(&mut reader, bin.as_ptr().cast(), bin.len());
         }
 
+        // The entry might have been produced by a different driver build (or a different
+        // device, for `CL_PROGRAM_BINARIES` round-tripping); reject it rather than risk loading
+        // a stale or mismatched compile.
+        let cached_name = Self::read_cache_str(&mut reader)?;
+        let cached_vendor = Self::read_cache_str(&mut reader)?;
+        let cached_cts_version = Self::read_cache_str(&mut reader)?;
+        let screen = d.screen();
+        if cached_name != screen.name()
+            || cached_vendor != screen.device_vendor()
+            || cached_cts_version != screen.cl_cts_version().to_string_lossy()
+        {
+            return None;
+        }
+
         let nir = NirShader::deserialize(
             &mut reader,
             d.screen()
@@ -876,6 +1068,7 @@ impl SPIRVToNirResult {
         )?;
         let args = KernelArg::deserialize(&mut reader)?;
         let internal_args = InternalKernelArg::deserialize(&mut reader)?;
+        let printf_uses_base_identifier = unsafe { blob_read_uint8(&mut reader) != 0 };
 
         Some(SPIRVToNirResult::new(
             d,
@@ -883,23 +1076,84 @@ impl SPIRVToNirResult {
             args,
             internal_args,
             nir,
+            printf_uses_base_identifier,
         ))
     }
 
     // we can't use Self here as the nir shader might be compiled to a cso already and we can't
-    // cache that.
+    // cache that; the driver-compiled `info` (private_memory/max_threads/...) lives on the CSO
+    // for the same reason and gets recomputed by creating it fresh, cache hit or not.
     fn serialize(
         blob: &mut blob,
+        dev: &Device,
         nir: &NirShader,
         args: &[KernelArg],
         internal_args: &[InternalKernelArg],
+        printf_uses_base_identifier: bool,
     ) {
+        let screen = dev.screen();
+        Self::write_cache_str(blob, &screen.name());
+        Self::write_cache_str(blob, &screen.device_vendor());
+        Self::write_cache_str(blob, &screen.cl_cts_version().to_string_lossy());
+
         nir.serialize(blob);
         KernelArg::serialize(args, blob);
         InternalKernelArg::serialize(internal_args, blob);
+        unsafe {
+            blob_write_uint8(blob, printf_uses_base_identifier.into());
+        }
+    }
+
+    /// Serializes into a standalone byte vector, used both for disk-cache entries and for
+    /// `CL_PROGRAM_BINARIES`/`clCreateProgramWithBinary` round-tripping.
+    fn to_bin(
+        dev: &Device,
+        nir: &NirShader,
+        args: &[KernelArg],
+        internal_args: &[InternalKernelArg],
+        printf_uses_base_identifier: bool,
+    ) -> Vec<u8> {
+        let mut blob = blob::default();
+        unsafe {
+            blob_init(&mut blob);
+            Self::serialize(
+                &mut blob,
+                dev,
+                nir,
+                args,
+                internal_args,
+                printf_uses_base_identifier,
+            );
+            let bin = slice::from_raw_parts(blob.data, blob.size).to_vec();
+            blob_finish(&mut blob);
+            bin
+        }
+    }
+
+    /// Reconstructs a build from a blob previously produced by [`Self::to_bin`], without
+    /// re-running [`lower_and_optimize_nir`]. Used for `clCreateProgramWithBinary`.
+    pub(super) fn from_program_binary(
+        bin: &[u8],
+        d: &'static Device,
+        kernel_info: &clc_kernel_info,
+    ) -> Option<Self> {
+        Self::deserialize(bin, d, kernel_info)
     }
 }
 
+/// Fetches the disk-cache entry backing `name`'s build on `dev`, in the same format
+/// `SPIRVToNirResult::deserialize` expects. Used to populate `CL_PROGRAM_BINARIES` with exactly
+/// the bytes a rebuild would have produced.
+pub(super) fn kernel_program_binary(
+    build: &ProgramBuild,
+    name: &str,
+    dev: &'static Device,
+) -> Option<Vec<u8>> {
+    let cache = dev.screen().shader_cache()?;
+    let mut key = build.hash_key(dev, name)?;
+    cache.get(&mut key)
+}
+
 pub(super) fn convert_spirv_to_nir(
     build: &ProgramBuild,
     name: &str,
@@ -916,20 +1170,28 @@ pub(super) fn convert_spirv_to_nir(
         .and_then(|entry| SPIRVToNirResult::deserialize(&entry, dev, spirv_info))
         .unwrap_or_else(|| {
             let mut nir = build.to_nir(name, dev);
-            let (args, internal_args) = lower_and_optimize_nir(dev, &mut nir, args, &dev.lib_clc);
+            let (args, internal_args, printf_uses_base_identifier) =
+                lower_and_optimize_nir(dev, &mut nir, args, &dev.lib_clc);
 
             if let Some(cache) = cache {
-                let mut blob = blob::default();
-                unsafe {
-                    blob_init(&mut blob);
-                    SPIRVToNirResult::serialize(&mut blob, &nir, &args, &internal_args);
-                    let bin = slice::from_raw_parts(blob.data, blob.size);
-                    cache.put(bin, &mut key.unwrap());
-                    blob_finish(&mut blob);
-                }
+                let bin = SPIRVToNirResult::to_bin(
+                    dev,
+                    &nir,
+                    &args,
+                    &internal_args,
+                    printf_uses_base_identifier,
+                );
+                cache.put(&bin, &mut key.unwrap());
             }
 
-            SPIRVToNirResult::new(dev, spirv_info, args, internal_args, nir)
+            SPIRVToNirResult::new(
+                dev,
+                spirv_info,
+                args,
+                internal_args,
+                nir,
+                printf_uses_base_identifier,
+            )
         })
 }
 
@@ -941,6 +1203,43 @@ fn extract<'a, const S: usize>(buf: &'a mut &[u8]) -> &'a [u8; S] {
     val.try_into().unwrap()
 }
 
+/// Decodes and delivers whatever complete printf records have been appended to `buf` (the full,
+/// just-mapped printf buffer, header included) since `consumed`, then advances `consumed` past
+/// them. Safe to call repeatedly against a buffer that's still being written by an in-flight
+/// kernel: the device only advances its write-offset header after a record's payload is fully
+/// written, so every byte below the header value is a complete record, never a torn one.
+fn drain_printf_buf(
+    buf: &[u8],
+    consumed: &AtomicU32,
+    printf_size: u32,
+    pf: &NirPrintfInfo,
+    printf_uses_base_identifier: bool,
+) {
+    let mut header = &buf[0..size_of::<u32>()];
+    let offset = u32::from_ne_bytes(*extract(&mut header));
+
+    // the kernel's write cursor can run past the buffer if it printed more than fit; clamp to
+    // what we actually have and let the user know output is missing instead of reading (or
+    // indexing) out of bounds.
+    if offset > printf_size {
+        eprintln!("rusticl: printf buffer overflowed, output truncated");
+    }
+
+    let valid_offset = cmp::min(offset, printf_size);
+    let prev_consumed = consumed.fetch_max(valid_offset, Ordering::AcqRel);
+    if valid_offset <= prev_consumed {
+        return;
+    }
+
+    // format strings stay host-side; when base-identifier mode is on, each record only carries
+    // a per-call identifier plus argument bytes and `pf` resolves it back to the format string
+    // using the base offset the lowering pass assigned.
+    pf.u_printf(
+        &buf[prev_consumed as usize..valid_offset as usize],
+        printf_uses_base_identifier,
+    );
+}
+
 impl Kernel {
     pub fn new(name: String, prog: Arc<Program>, prog_build: &ProgramBuild) -> Arc<Kernel> {
         let kernel_info = Arc::clone(prog_build.kernel_info.get(&name).unwrap());
@@ -957,10 +1256,30 @@ impl Kernel {
             name: name,
             values: Mutex::new(values),
             builds: builds,
+            specialized_builds: Mutex::new(HashMap::new()),
             kernel_info: kernel_info,
+            spec_constants: prog_build.spec_constants.clone(),
+            indirect_mem: Mutex::new(None),
         })
     }
 
+    /// The `spec_constants` snapshot this kernel's `builds`/`kernel_info` were compiled against.
+    pub fn spec_constants(&self) -> &SpecConstants {
+        &self.spec_constants
+    }
+
+    /// Backs `clSetKernelExecInfo(CL_KERNEL_EXEC_INFO_SVM_PTRS)` and the buffer-pointer variant:
+    /// records the concrete allocations `mems` resolved to so `launch` can keep them resident.
+    pub fn set_indirect_mem_specific(&self, mems: Vec<Arc<Buffer>>) {
+        *self.indirect_mem.lock().unwrap() = Some(IndirectMem::Specific(mems));
+    }
+
+    /// Backs `clSetKernelExecInfo(CL_KERNEL_EXEC_INFO_INDIRECT_{HOST,DEVICE}_ACCESS)`: tells
+    /// `launch` to conservatively make every live SVM/global allocation in the context resident.
+    pub fn set_indirect_mem_all(&self) {
+        *self.indirect_mem.lock().unwrap() = Some(IndirectMem::All);
+    }
+
     pub fn suggest_local_size(
         &self,
         d: &Device,
@@ -972,6 +1291,31 @@ impl Kernel {
         let dim_threads = d.max_block_sizes();
         let subgroups = self.preferred_simd_size(d);
 
+        // Prefer a workgroup that's a whole multiple of the preferred SIMD width, so we don't
+        // waste occupancy on a partially filled subgroup. Fall back to the raw hardware max
+        // (the previous behavior) if that would round the budget away entirely.
+        if subgroups > 1 {
+            let rounded = (threads / subgroups) * subgroups;
+            if rounded > 0 {
+                threads = rounded;
+            }
+        }
+
+        // A kernel that spills a lot of private data per invocation needs proportionally more
+        // register file/scratch space per thread than the driver's flat `max_threads` limit
+        // assumes, so back the budget off further once the spill is large enough to matter.
+        // `PRIV_MEM_DERATE_GRANULE` is a coarse stand-in for the allocation granule GPUs tend to
+        // use for spilled registers.
+        const PRIV_MEM_DERATE_GRANULE: usize = 256;
+        let priv_mem = self.kernel_info.priv_mem_size as usize;
+        if priv_mem > PRIV_MEM_DERATE_GRANULE {
+            let derate = priv_mem / PRIV_MEM_DERATE_GRANULE;
+            let derated = threads / derate;
+            if derated > 0 {
+                threads = derated;
+            }
+        }
+
         for i in 0..work_dim {
             let t = cmp::min(threads, dim_threads[i]);
             let gcd = gcd(t, grid[i]);
@@ -997,13 +1341,23 @@ impl Kernel {
         }
     }
 
-    fn optimize_local_size(&self, d: &Device, grid: &mut [usize; 3], block: &mut [u32; 3]) {
+    /// Divides `grid` (global work-item counts) down to work-group counts for `block`, returning
+    /// the per-axis remainder work-item count for non-uniform ND-ranges (0 when `grid[i]` is an
+    /// exact multiple of `block[i]`, which is always the case for the suggested-size path).
+    fn optimize_local_size(
+        &self,
+        d: &Device,
+        grid: &mut [usize; 3],
+        block: &mut [u32; 3],
+    ) -> [u32; 3] {
         if !block.contains(&0) {
+            let mut remainder = [0u32; 3];
             for i in 0..3 {
-                // we already made sure everything is fine
-                grid[i] /= block[i] as usize;
+                let b = block[i] as usize;
+                remainder[i] = (grid[i] % b) as u32;
+                grid[i] /= b;
             }
-            return;
+            return remainder;
         }
 
         let mut usize_block = [0usize; 3];
@@ -1016,6 +1370,28 @@ impl Kernel {
         for i in 0..3 {
             block[i] = usize_block[i] as u32;
         }
+
+        [0; 3]
+    }
+
+    /// Returns the build to launch with for this concrete `block` size, specializing and
+    /// caching a new one in `specialized_builds` on first use of a given size, and falling back
+    /// to the generic variable-size build when the device doesn't retain a source NIR to
+    /// specialize from.
+    fn build_for_block(&self, dev: &'static Device, block: [u32; 3]) -> Arc<NirKernelBuild> {
+        let generic = &self.builds[dev];
+
+        if generic.source_nir.is_none() {
+            return Arc::clone(generic);
+        }
+
+        let mut specialized = self.specialized_builds.lock().unwrap();
+        Arc::clone(specialized.entry((dev, block)).or_insert_with(|| {
+            generic
+                .specialize_for_block(dev, block)
+                .map(Arc::new)
+                .unwrap_or_else(|| Arc::clone(generic))
+        }))
     }
 
     // the painful part is, that host threads are allowed to modify the kernel object once it was
@@ -1031,14 +1407,32 @@ impl Kernel {
         // Clone all the data we need to execute this kernel
         let kernel_info = Arc::clone(&self.kernel_info);
         let arg_values = self.arg_values().clone();
-        let nir_kernel_build = Arc::clone(&self.builds[q.device]);
+        let indirect_mem = self.indirect_mem.lock().unwrap().clone();
 
         // operations we want to report errors to the clients
         let mut block = create_kernel_arr::<u32>(block, 1)?;
         let mut grid = create_kernel_arr::<usize>(grid, 1)?;
         let offsets = create_kernel_arr::<usize>(offsets, 0)?;
 
-        self.optimize_local_size(q.device, &mut grid, &mut block);
+        let work_dim_usize = work_dim as usize;
+        let mut remainder = self.optimize_local_size(q.device, &mut grid, &mut block);
+        if !q.device.supports_non_uniform_work_groups() {
+            remainder = [0; 3];
+        }
+        // only the active dimensions can have a remainder; higher ones are fixed at size 1
+        for r in remainder.iter_mut().skip(work_dim_usize) {
+            *r = 0;
+        }
+
+        // once `optimize_local_size` resolved every dimension, see if we have (or can build) a
+        // build specialized for exactly this local size; it trades a bit of extra compilation
+        // for better codegen on hot kernels with a known work-group size. Remainder tiles are
+        // dispatched with other block sizes, so those launches keep the generic build.
+        let nir_kernel_build = if remainder == [0; 3] {
+            self.build_for_block(q.device, block)
+        } else {
+            Arc::clone(&self.builds[q.device])
+        };
 
         Ok(Box::new(move |q, ctx| {
             let mut workgroup_id_offset_loc = None;
@@ -1189,7 +1583,13 @@ impl Kernel {
                     .unwrap();
 
                 let init_data: [u8; 1] = [4];
-                ctx.buffer_subdata(&buf, 0, init_data.as_ptr().cast(), init_data.len() as u32);
+                ctx.buffer_subdata(
+                    &buf,
+                    0,
+                    init_data.as_ptr().cast(),
+                    init_data.len() as u32,
+                    MapFlags::new(RWFlags::WR).discard_whole_resource(),
+                );
 
                 printf_buf = Some(buf);
             }
@@ -1246,9 +1646,14 @@ impl Kernel {
                         input.extend_from_slice(&[work_dim as u8; 1]);
                     }
                     InternalKernelArgType::NumWorkgroups => {
-                        input.extend_from_slice(unsafe {
-                            as_byte_slice(&[grid[0] as u32, grid[1] as u32, grid[2] as u32])
-                        });
+                        // with a non-uniform ND-range the trailing remainder tile is still a
+                        // (partial) work-group, so get_num_groups needs to round up for it.
+                        let num_groups = [
+                            grid[0] as u32 + u32::from(remainder[0] != 0),
+                            grid[1] as u32 + u32::from(remainder[1] != 0),
+                            grid[2] as u32 + u32::from(remainder[2] != 0),
+                        ];
+                        input.extend_from_slice(unsafe { as_byte_slice(&num_groups) });
                     }
                 }
             }
@@ -1262,6 +1667,25 @@ impl Kernel {
                 .map(|s| ctx.create_sampler_state(s))
                 .collect();
 
+            // `indirect_mem` isn't bound to any kernel argument, so there's no input slot to
+            // patch a pointer into; `add_global` still reserves the scratch bytes `globals`
+            // needs to receive the resolved GPU address, they just go unread by the shader.
+            match &indirect_mem {
+                Some(IndirectMem::Specific(mems)) => {
+                    for mem in mems {
+                        let res = mem.get_res_of_dev(q.device)?;
+                        add_global(q, &mut input, &mut resource_info, res, 0);
+                    }
+                }
+                Some(IndirectMem::All) => {
+                    for mem in q.context.indirect_mem_allocations() {
+                        let res = mem.get_res_of_dev(q.device)?;
+                        add_global(q, &mut input, &mut resource_info, res, 0);
+                    }
+                }
+                None => (),
+            }
+
             let mut resources = Vec::with_capacity(resource_info.len());
             let mut globals: Vec<*mut u32> = Vec::with_capacity(resource_info.len());
             for (res, offset) in resource_info {
@@ -1293,41 +1717,117 @@ impl Kernel {
                 .map(|val| cmp::min(val, u32::MAX as usize))
                 .collect();
 
-            for z in 0..div_round_up(grid[2], hw_max_grid[2]) {
-                for y in 0..div_round_up(grid[1], hw_max_grid[1]) {
-                    for x in 0..div_round_up(grid[0], hw_max_grid[0]) {
-                        if let Some(workgroup_id_offset_loc) = workgroup_id_offset_loc {
-                            let this_offsets =
-                                [x * hw_max_grid[0], y * hw_max_grid[1], z * hw_max_grid[2]];
-
-                            if q.device.address_bits() == 64 {
-                                let val = this_offsets.map(|v| v as u64);
-                                input[workgroup_id_offset_loc..workgroup_id_offset_loc + 24]
-                                    .copy_from_slice(unsafe { as_byte_slice(&val) });
-                            } else {
-                                let val = this_offsets.map(|v| v as u32);
-                                input[workgroup_id_offset_loc..workgroup_id_offset_loc + 12]
-                                    .copy_from_slice(unsafe { as_byte_slice(&val) });
-                            }
+            // A non-uniform ND-range splits each axis into a uniform region (`grid[i]` full
+            // work-groups of size `block[i]`) and, if `remainder[i]` is non-zero, one trailing
+            // group of that odd size. Every combination of "uniform vs remainder" across the
+            // active axes needs its own dispatch, since a single `launch_grid` can't vary the
+            // work-group size within one call.
+            let printf_consumed = AtomicU32::new(size_of::<u32>() as u32);
+            let stop_printf_stream = AtomicBool::new(false);
+
+            thread::scope(|scope| -> CLResult<()> {
+                // Stream out printf records that are already complete while the dispatches below
+                // are still in flight, instead of only harvesting them once everything finishes;
+                // a long-running (or hung) kernel then still produces output as it goes. This is
+                // best-effort only -- the unsynchronized map never blocks and may observe a
+                // slightly stale write offset -- the synchronized drain after the dispatch loop
+                // below remains the source of truth for what actually got printed.
+                if let (Some(printf_buf), Some(pf)) = (&printf_buf, &nir_kernel_build.printf_info) {
+                    scope.spawn(|| {
+                        while !stop_printf_stream.load(Ordering::Acquire) {
+                            thread::sleep(Duration::from_millis(2));
+                            let tx = ctx.buffer_map(
+                                printf_buf,
+                                0,
+                                printf_size as i32,
+                                MapFlags::new(RWFlags::RD).map_type(ResourceMapType::Async),
+                            );
+                            let buf = unsafe {
+                                slice::from_raw_parts(tx.ptr().cast(), printf_size as usize)
+                            };
+                            drain_printf_buf(
+                                buf,
+                                &printf_consumed,
+                                printf_size,
+                                pf,
+                                nir_kernel_build.printf_uses_base_identifier,
+                            );
                         }
+                    });
+                }
 
-                        let this_grid = [
-                            cmp::min(hw_max_grid[0], grid[0] - hw_max_grid[0] * x) as u32,
-                            cmp::min(hw_max_grid[1], grid[1] - hw_max_grid[1] * y) as u32,
-                            cmp::min(hw_max_grid[2], grid[2] - hw_max_grid[2] * z) as u32,
-                        ];
+                'combo: for combo in 0..(1u32 << 3) {
+                    let mut sub_block = block;
+                    let mut sub_groups = grid;
+                    let mut group_base = [0usize; 3];
 
-                        ctx.update_cb0(&input)?;
-                        ctx.launch_grid(work_dim, block, this_grid, variable_local_size as u32);
+                    for dim in 0..3 {
+                        if (combo >> dim) & 1 == 0 {
+                            continue;
+                        }
+                        if remainder[dim] == 0 {
+                            // this axis has no remainder tile, so this combo is a duplicate of
+                            // one we already covered with the bit cleared
+                            continue 'combo;
+                        }
+                        sub_block[dim] = remainder[dim];
+                        sub_groups[dim] = 1;
+                        group_base[dim] = grid[dim];
+                    }
 
-                        if Platform::dbg().sync_every_event {
-                            ctx.flush().wait();
+                    for z in 0..div_round_up(sub_groups[2], hw_max_grid[2]) {
+                        for y in 0..div_round_up(sub_groups[1], hw_max_grid[1]) {
+                            for x in 0..div_round_up(sub_groups[0], hw_max_grid[0]) {
+                                if let Some(workgroup_id_offset_loc) = workgroup_id_offset_loc {
+                                    let this_offsets = [
+                                        group_base[0] + x * hw_max_grid[0],
+                                        group_base[1] + y * hw_max_grid[1],
+                                        group_base[2] + z * hw_max_grid[2],
+                                    ];
+
+                                    if q.device.address_bits() == 64 {
+                                        let val = this_offsets.map(|v| v as u64);
+                                        input[workgroup_id_offset_loc..workgroup_id_offset_loc + 24]
+                                            .copy_from_slice(unsafe { as_byte_slice(&val) });
+                                    } else {
+                                        let val = this_offsets.map(|v| v as u32);
+                                        input[workgroup_id_offset_loc..workgroup_id_offset_loc + 12]
+                                            .copy_from_slice(unsafe { as_byte_slice(&val) });
+                                    }
+                                }
+
+                                let this_grid = [
+                                    cmp::min(hw_max_grid[0], sub_groups[0] - hw_max_grid[0] * x)
+                                        as u32,
+                                    cmp::min(hw_max_grid[1], sub_groups[1] - hw_max_grid[1] * y)
+                                        as u32,
+                                    cmp::min(hw_max_grid[2], sub_groups[2] - hw_max_grid[2] * z)
+                                        as u32,
+                                ];
+
+                                ctx.update_cb0(&input)?;
+                                ctx.launch_grid(
+                                    work_dim,
+                                    sub_block,
+                                    this_grid,
+                                    variable_local_size as u32,
+                                );
+
+                                if Platform::dbg().sync_every_event {
+                                    ctx.flush().wait();
+                                }
+                            }
                         }
                     }
                 }
-            }
+
+                stop_printf_stream.store(true, Ordering::Release);
+
+                Ok(())
+            })?;
 
             ctx.clear_global_binding(globals.len() as u32);
+
             ctx.clear_shader_images(iviews.len() as u32);
             ctx.clear_sampler_views(sviews.len() as u32);
             ctx.clear_sampler_states(samplers.len() as u32);
@@ -1339,18 +1839,27 @@ impl Kernel {
             samplers.iter().for_each(|s| ctx.delete_sampler_state(*s));
             sviews.iter().for_each(|v| ctx.sampler_view_destroy(*v));
 
+            // The streaming worker above only ever gives a preview; this map is synchronized, so
+            // by the time it returns every dispatch has actually completed and its printf output
+            // is guaranteed visible. Resume from wherever the worker left off rather than
+            // re-decoding (and re-printing) records it already delivered.
             if let Some(printf_buf) = &printf_buf {
-                let tx = ctx
-                    .buffer_map(printf_buf, 0, printf_size as i32, RWFlags::RD)
-                    .ok_or(CL_OUT_OF_RESOURCES)?;
-                let mut buf: &[u8] =
+                let tx = ctx.buffer_map(
+                    printf_buf,
+                    0,
+                    printf_size as i32,
+                    MapFlags::new(RWFlags::RD).map_type(ResourceMapType::Normal),
+                );
+                let buf =
                     unsafe { slice::from_raw_parts(tx.ptr().cast(), printf_size as usize) };
-                let length = u32::from_ne_bytes(*extract(&mut buf));
-
-                // update our slice to make sure we don't go out of bounds
-                buf = &buf[0..(length - 4) as usize];
                 if let Some(pf) = &nir_kernel_build.printf_info {
-                    pf.u_printf(buf)
+                    drain_printf_buf(
+                        buf,
+                        &printf_consumed,
+                        printf_size,
+                        pf,
+                        nir_kernel_build.printf_uses_base_identifier,
+                    );
                 }
             }
 
@@ -1446,8 +1955,11 @@ impl Kernel {
         &self.kernel_info.args[idx as usize].spirv.type_name
     }
 
+    /// Backs `CL_KERNEL_PRIVATE_MEM_SIZE`: the compiler's static scratch allocation plus whatever
+    /// extra per-invocation storage this device's backend spilled on top of it compiling the CSO.
     pub fn priv_mem_size(&self, dev: &Device) -> cl_ulong {
-        self.builds.get(dev).unwrap().info.private_memory as cl_ulong
+        self.kernel_info.priv_mem_size as cl_ulong
+            + self.builds.get(dev).unwrap().info.private_memory as cl_ulong
     }
 
     pub fn max_threads_per_block(&self, dev: &Device) -> usize {
@@ -1458,9 +1970,19 @@ impl Kernel {
         self.builds.get(dev).unwrap().info.preferred_simd_size as usize
     }
 
-    pub fn local_mem_size(&self, dev: &Device) -> cl_ulong {
-        // TODO include args
-        self.builds.get(dev).unwrap().shared_size as cl_ulong
+    /// Backs `CL_KERNEL_LOCAL_MEM_SIZE`: the compile-time static allocation plus whatever
+    /// `__local` kernel arguments are currently set to.
+    pub fn local_mem_size(&self, _dev: &Device) -> cl_ulong {
+        let local_args_size: usize = self
+            .arg_values()
+            .iter()
+            .filter_map(|val| match val {
+                Some(KernelArgValue::LocalMem(size)) => Some(*size),
+                _ => None,
+            })
+            .sum();
+
+        self.kernel_info.local_mem_size as cl_ulong + local_args_size as cl_ulong
     }
 
     pub fn has_svm_devs(&self) -> bool {
@@ -1509,6 +2031,18 @@ impl Kernel {
             }
         }
     }
+
+    /// 1D work-group size made of exactly `sub_group_count` full subgroups, as queried by
+    /// `CL_KERNEL_LOCAL_SIZE_FOR_SUB_GROUP_COUNT`. Assumes a fixed subgroup size, as
+    /// we have no concrete local size to disambiguate a variable one.
+    pub fn local_size_for_sub_group_count(&self, dev: &Device, sub_group_count: usize) -> usize {
+        let subgroup_sizes = self.subgroup_sizes(dev);
+        if subgroup_sizes.len() != 1 || sub_group_count == 0 {
+            return 0;
+        }
+
+        subgroup_sizes[0] * sub_group_count
+    }
 }
 
 impl Clone for Kernel {
@@ -1519,7 +2053,72 @@ impl Clone for Kernel {
             name: self.name.clone(),
             values: Mutex::new(self.arg_values().clone()),
             builds: self.builds.clone(),
+            specialized_builds: Mutex::new(self.specialized_builds.lock().unwrap().clone()),
             kernel_info: self.kernel_info.clone(),
+            spec_constants: self.spec_constants.clone(),
+            indirect_mem: Mutex::new(self.indirect_mem.lock().unwrap().clone()),
         }
     }
 }
+
+/// A host-side callback command enqueued via `clEnqueueNativeKernel`, alongside `Kernel` for GPU
+/// dispatches. Argument marshaling reuses the same shape as `KernelArgValue`: the caller supplies
+/// a raw argument blob plus, for every referenced memory object, the byte offset into that blob
+/// where its mapped host pointer belongs.
+pub struct NativeKernel {
+    user_func: unsafe extern "C" fn(*mut c_void),
+    args: Vec<u8>,
+    /// Memory objects the native function dereferences through `args`, paired with the offset
+    /// of the pointer slot each one's mapped address gets patched into.
+    mem_args: Vec<(Arc<Buffer>, usize)>,
+}
+
+impl NativeKernel {
+    pub fn new(
+        user_func: unsafe extern "C" fn(*mut c_void),
+        args: Vec<u8>,
+        mem_args: Vec<(Arc<Buffer>, usize)>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            user_func,
+            args,
+            mem_args,
+        })
+    }
+
+    /// Builds the `EventSig` the queue runs once every command this one depends on has
+    /// completed, same barrier/flush discipline as `Kernel::launch`: the maps below are
+    /// synchronized, so they themselves wait out any pending GPU writes to the mapped buffers
+    /// before the native function ever sees a pointer into them.
+    pub fn launch(self: &Arc<Self>, _q: &Arc<Queue>) -> CLResult<EventSig> {
+        let nk = Arc::clone(self);
+        Ok(Box::new(move |q, ctx| {
+            let mut args = nk.args.clone();
+
+            // Transfers have to stay alive for the whole callback: the host pointers patched
+            // into `args` are only valid while their mapping is held.
+            let mut transfers = Vec::with_capacity(nk.mem_args.len());
+            for (mem, offset) in &nk.mem_args {
+                let res = mem.get_res_of_dev(q.device)?;
+                let tx = ctx.buffer_map(
+                    res,
+                    mem.offset as i32,
+                    mem.size as i32,
+                    MapFlags::new(RWFlags::RW).map_type(ResourceMapType::Normal),
+                );
+
+                let dst = args[*offset..*offset + size_of::<*mut c_void>()].as_mut_ptr();
+                unsafe {
+                    ptr::write_unaligned(dst.cast::<*mut c_void>(), tx.ptr());
+                }
+                transfers.push(tx);
+            }
+
+            unsafe {
+                (nk.user_func)(args.as_mut_ptr().cast());
+            }
+
+            Ok(())
+        }))
+    }
+}
@@ -6,15 +6,23 @@ use crate::pipe::transfer::*;
 
 use mesa_rust_gen::*;
 use mesa_rust_util::has_required_feature;
+use mesa_rust_util::math::Minify;
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::os::raw::*;
 use std::ptr;
 use std::ptr::*;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 pub struct PipeContext {
     pipe: NonNull<pipe_context>,
     screen: Arc<PipeScreen>,
+    mappings: Mappings,
+    has_queries: bool,
+    lazy_init: LazyInit,
 }
 
 unsafe impl Send for PipeContext {}
@@ -54,11 +62,151 @@ impl From<ResourceMapType> for pipe_map_flags {
     }
 }
 
+/// Builds up the `pipe_map_flags` for a single map/subdata call from [`RWFlags`], an optional
+/// [`ResourceMapType`], and the discard/invalidate hints Gallium frontends are expected to tag
+/// their internal maps with. Keeping the pieces composable (rather than one flat enum) lets
+/// callers like `buffer_subdata`'s internal write-only uploads opt into `discard_whole_resource`
+/// without dragging every other map call site through the same flags.
+#[derive(Clone, Copy)]
+pub struct MapFlags(pipe_map_flags);
+
+impl MapFlags {
+    pub fn new(rw: RWFlags) -> Self {
+        Self(rw.into())
+    }
+
+    pub fn map_type(mut self, map_type: ResourceMapType) -> Self {
+        self.0 |= map_type.into();
+        self
+    }
+
+    /// Hints that the driver only needs to preserve the mapped range, not the whole resource, so
+    /// it can discard any stale contents within it instead of synchronizing with in-flight reads.
+    pub fn discard_range(mut self) -> Self {
+        self.0 |= pipe_map_flags::PIPE_MAP_DISCARD_RANGE;
+        self
+    }
+
+    /// Hints that none of the resource's prior contents need to survive the map, so the driver
+    /// can orphan the backing allocation and hand back a fresh one instead of stalling on
+    /// in-flight reads of the old one.
+    pub fn discard_whole_resource(mut self) -> Self {
+        self.0 |= pipe_map_flags::PIPE_MAP_DISCARD_WHOLE_RESOURCE;
+        self
+    }
+
+    /// Hints that the caller doesn't care about the current contents of the mapped region, only
+    /// about writing to it.
+    pub fn invalidate(mut self) -> Self {
+        self.0 |= pipe_map_flags::PIPE_MAP_INVALIDATE;
+        self
+    }
+}
+
+impl From<MapFlags> for pipe_map_flags {
+    fn from(flags: MapFlags) -> Self {
+        flags.0
+    }
+}
+
+/// Backing state for one resource's coalesced CPU mapping: the transfer the driver gave us, the
+/// region it covers (needed to copy a shadow's contents back on unmap), an optional shadow
+/// resource when the real one can't be mapped directly, and how many logical
+/// (`clEnqueueMapBuffer`-level) maps currently share it.
+struct MappingTransfer {
+    tx: PipeTransfer,
+    shadow: Option<PipeResource>,
+    offset: i32,
+    size: i32,
+    pending: u32,
+}
+
+/// Coalesces overlapping logical maps of the same resource into a single underlying pipe
+/// transfer and falls back to a linear staging/shadow resource -- copied in on the first map,
+/// copied back on the last writable unmap -- for resources that can't be mapped directly. This
+/// is what gives `clEnqueueMapBuffer`/`clEnqueueUnmapMemObject` correct reference-counting
+/// semantics: repeated maps of the same resource return the same host pointer, and only the
+/// unmap that drops the last reference actually tears the mapping down.
+#[derive(Default)]
+struct Mappings {
+    transfers: Mutex<HashMap<*mut pipe_resource, MappingTransfer>>,
+    ptr_refs: Mutex<HashMap<*mut c_void, u32>>,
+}
+
+// `*mut pipe_resource`/`*mut c_void` keys are never dereferenced on their own; they're only ever
+// used as map keys alongside the `PipeContext` (itself `Send + Sync`) that owns the resources
+// they identify.
+unsafe impl Send for Mappings {}
+unsafe impl Sync for Mappings {}
+
+/// A sub-range of a [`ResourceType::Cleared`] resource to make sure has been zeroed, either a
+/// byte range of a buffer or a set of mip-level/array-layer subresources of a texture.
+pub enum InitRange {
+    Buffer { offset: u32, size: u32 },
+    Texture {
+        level: u32,
+        first_layer: u32,
+        layer_count: u32,
+    },
+}
+
+/// Per-resource lazy zero-initialization bookkeeping for resources created with
+/// [`ResourceType::Cleared`]: which byte ranges of a buffer, or which `(level, layer)`
+/// subresources of a texture, have already been zeroed. Starts out empty (nothing initialized)
+/// and only grows as [`PipeContext::ensure_initialized`] fills in the gaps actually requested,
+/// so a large allocation that ends up fully overwritten anyway never gets eagerly zeroed.
+#[derive(Default)]
+struct LazyInit {
+    buffers: Mutex<HashMap<*mut pipe_resource, Vec<Range<u32>>>>,
+    textures: Mutex<HashMap<*mut pipe_resource, HashSet<(u32, u32)>>>,
+}
+
+// See the `Mappings` comment above -- same reasoning applies to these keys.
+unsafe impl Send for LazyInit {}
+unsafe impl Sync for LazyInit {}
+
+/// Subtracts `b` from `a`, returning the 0, 1 or 2 pieces of `a` left uncovered.
+fn range_subtract(a: Range<u32>, b: &Range<u32>) -> Vec<Range<u32>> {
+    if b.end <= a.start || b.start >= a.end {
+        return vec![a];
+    }
+
+    let mut out = Vec::new();
+    if a.start < b.start {
+        out.push(a.start..b.start);
+    }
+    if b.end < a.end {
+        out.push(b.end..a.end);
+    }
+    out
+}
+
+/// Merges `ranges` in place, collapsing any overlapping or touching entries, to keep the tracked
+/// set from growing without bound as `ensure_initialized` is called repeatedly.
+fn ranges_coalesce(ranges: &mut Vec<Range<u32>>) {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<u32>> = Vec::with_capacity(ranges.len());
+    for r in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    *ranges = merged;
+}
+
 impl PipeContext {
     pub(super) fn new(context: *mut pipe_context, screen: &Arc<PipeScreen>) -> Option<Self> {
+        let pipe = NonNull::new(context)?;
         let s = Self {
-            pipe: NonNull::new(context)?,
+            pipe,
             screen: screen.clone(),
+            mappings: Mappings::default(),
+            // Profiling (CL_PROFILING_COMMAND_START/END) is a nice-to-have, not a hard
+            // requirement, so a driver missing query support shouldn't fail context creation --
+            // just disable it and have the query methods below degrade to `None`/no-ops.
+            has_queries: has_query_cbs(unsafe { pipe.as_ref() }),
+            lazy_init: LazyInit::default(),
         };
 
         if !has_required_cbs(unsafe { s.pipe.as_ref() }) {
@@ -75,12 +223,13 @@ impl PipeContext {
         offset: c_uint,
         data: *const c_void,
         size: c_uint,
+        flags: MapFlags,
     ) {
         unsafe {
             self.pipe.as_ref().buffer_subdata.unwrap()(
                 self.pipe.as_ptr(),
                 res.pipe(),
-                pipe_map_flags::PIPE_MAP_WRITE.0, // TODO PIPE_MAP_x
+                pipe_map_flags::from(flags).0,
                 offset,
                 size,
                 data,
@@ -95,13 +244,14 @@ impl PipeContext {
         data: *const c_void,
         stride: u32,
         layer_stride: u32,
+        flags: MapFlags,
     ) {
         unsafe {
             self.pipe.as_ref().texture_subdata.unwrap()(
                 self.pipe.as_ptr(),
                 res.pipe(),
                 0,
-                pipe_map_flags::PIPE_MAP_WRITE.0, // TODO PIPE_MAP_x
+                pipe_map_flags::from(flags).0,
                 bx,
                 data,
                 stride,
@@ -123,18 +273,121 @@ impl PipeContext {
         }
     }
 
-    pub fn clear_texture(&self, res: &PipeResource, pattern: &[u32], bx: &pipe_box) {
+    pub fn clear_texture(&self, res: &PipeResource, level: u32, pattern: &[u32], bx: &pipe_box) {
         unsafe {
             self.pipe.as_ref().clear_texture.unwrap()(
                 self.pipe.as_ptr(),
                 res.pipe(),
-                0,
+                level,
                 bx,
                 pattern.as_ptr().cast(),
             )
         }
     }
 
+    /// Makes sure `range` of `res` -- a resource created with [`ResourceType::Cleared`] -- has
+    /// actually been zeroed, issuing a `clear_buffer`/`clear_texture` over just the sub-ranges not
+    /// already covered by a previous `ensure_initialized` call (or skip it, if a full write
+    /// already marked them via [`Self::mark_initialized`]). Safe to call redundantly: once a
+    /// range is tracked as initialized, later calls covering it are a no-op.
+    pub fn ensure_initialized(&self, res: &PipeResource, range: InitRange) {
+        match range {
+            InitRange::Buffer { offset, size } => self.ensure_buffer_initialized(res, offset, size),
+            InitRange::Texture {
+                level,
+                first_layer,
+                layer_count,
+            } => self.ensure_texture_initialized(res, level, first_layer, layer_count),
+        }
+    }
+
+    /// Marks `range` of `res` as initialized without clearing it, because the caller is about to
+    /// (or just did) write every byte/subresource in it -- e.g. a full-resource upload has no
+    /// need to zero first only to immediately overwrite that zero-fill.
+    pub fn mark_initialized(&self, res: &PipeResource, range: InitRange) {
+        match range {
+            InitRange::Buffer { offset, size } => {
+                let mut buffers = self.lazy_init.buffers.lock().unwrap();
+                let ranges = buffers.entry(res.pipe()).or_default();
+                ranges.push(offset..offset + size);
+                ranges_coalesce(ranges);
+            }
+            InitRange::Texture {
+                level,
+                first_layer,
+                layer_count,
+            } => {
+                let mut textures = self.lazy_init.textures.lock().unwrap();
+                let inited = textures.entry(res.pipe()).or_default();
+                for layer in first_layer..first_layer + layer_count {
+                    inited.insert((level, layer));
+                }
+            }
+        }
+    }
+
+    fn ensure_buffer_initialized(&self, res: &PipeResource, offset: u32, size: u32) {
+        let mut buffers = self.lazy_init.buffers.lock().unwrap();
+        let ranges = buffers.entry(res.pipe()).or_default();
+
+        let mut gaps = vec![offset..offset + size];
+        for r in ranges.iter() {
+            gaps = gaps.into_iter().flat_map(|g| range_subtract(g, r)).collect();
+        }
+
+        for gap in &gaps {
+            self.clear_buffer(res, &[0u8; 4], gap.start, gap.end - gap.start);
+        }
+
+        ranges.push(offset..offset + size);
+        ranges_coalesce(ranges);
+    }
+
+    fn ensure_texture_initialized(
+        &self,
+        res: &PipeResource,
+        level: u32,
+        first_layer: u32,
+        layer_count: u32,
+    ) {
+        let mut textures = self.lazy_init.textures.lock().unwrap();
+        let inited = textures.entry(res.pipe()).or_default();
+
+        let missing: Vec<u32> = (first_layer..first_layer + layer_count)
+            .filter(|layer| !inited.contains(&(level, *layer)))
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let extent = Self::resource_extent(res).minify(level);
+        let bx = |first: u32, count: u32| pipe_box {
+            x: 0,
+            y: 0,
+            z: first as i32,
+            width: extent.width as i32,
+            height: extent.height as i32,
+            depth: count as i32,
+        };
+
+        // Clear contiguous runs of missing layers with a single call rather than one per layer.
+        let mut run_start = missing[0];
+        let mut run_end = missing[0];
+        for &layer in &missing[1..] {
+            if layer != run_end + 1 {
+                self.clear_texture(res, level, &[0; 4], &bx(run_start, run_end - run_start + 1));
+                run_start = layer;
+            }
+            run_end = layer;
+        }
+        self.clear_texture(res, level, &[0; 4], &bx(run_start, run_end - run_start + 1));
+
+        for &layer in &missing {
+            inited.insert((level, layer));
+        }
+    }
+
     pub fn resource_copy_region(
         &self,
         src: &PipeResource,
@@ -157,6 +410,138 @@ impl PipeContext {
         }
     }
 
+    fn resource_format(res: &PipeResource) -> pipe_format {
+        unsafe { (*res.pipe()).format }
+    }
+
+    fn resource_extent(res: &PipeResource) -> Extent4D {
+        unsafe {
+            let r = &*res.pipe();
+            Extent4D::new(r.width0, r.height0, r.depth0, r.array_size)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn blit_mip(
+        &self,
+        src: &PipeResource,
+        src_level: u32,
+        src_box: &pipe_box,
+        src_format: pipe_format,
+        dst: &PipeResource,
+        dst_level: u32,
+        dst_box: &pipe_box,
+        dst_format: pipe_format,
+        filter: pipe_tex_filter,
+        mask: u32,
+    ) {
+        let info = pipe_blit_info {
+            dst: pipe_blit_info__bindgen_ty_1 {
+                resource: dst.pipe(),
+                level: dst_level,
+                box_: *dst_box,
+                format: dst_format,
+            },
+            src: pipe_blit_info__bindgen_ty_1 {
+                resource: src.pipe(),
+                level: src_level,
+                box_: *src_box,
+                format: src_format,
+            },
+            mask,
+            filter: filter as u32,
+            ..Default::default()
+        };
+
+        unsafe { self.pipe.as_ref().blit.unwrap()(self.pipe.as_ptr(), &info) }
+    }
+
+    /// Format-converting, optionally-scaling blit of `src_box` of `src` into `dst_box` of `dst`,
+    /// going through `util_blitter` on drivers that can't do it natively. This is what
+    /// `clEnqueueCopyImage` needs for anything beyond a same-size, same-format copy --
+    /// `resource_copy_region` only handles that narrower case.
+    pub fn blit(
+        &self,
+        src: &PipeResource,
+        dst: &PipeResource,
+        src_box: &pipe_box,
+        dst_box: &pipe_box,
+        filter: pipe_tex_filter,
+        mask: u32,
+    ) {
+        self.blit_mip(
+            src,
+            0,
+            src_box,
+            Self::resource_format(src),
+            dst,
+            0,
+            dst_box,
+            Self::resource_format(dst),
+            filter,
+            mask,
+        )
+    }
+
+    /// Fills in `res`'s mip levels `base_level + 1 ..= last_level` from `base_level` by blitting
+    /// each level down into the next, halving the width/height/depth of the previous level's
+    /// extent (but not its array length) at every step via [`Extent4D::minify`]. `first_layer`/
+    /// `last_layer` bound the array slices (or, for volume textures, nothing -- the depth itself
+    /// is what gets minified) regenerated at each level, mirroring `u_gen_mipmap`.
+    pub fn generate_mipmap(
+        &self,
+        res: &PipeResource,
+        base_level: u32,
+        last_level: u32,
+        first_layer: u32,
+        last_layer: u32,
+        format: pipe_format,
+    ) {
+        let base_extent = Self::resource_extent(res);
+        let layers = (last_layer - first_layer + 1) as i32;
+        let is_3d = unsafe { (*res.pipe()).target() } == pipe_texture_target::PIPE_TEXTURE_3D;
+
+        for level in base_level..last_level {
+            let src_extent = base_extent.minify(level);
+            let dst_extent = base_extent.minify(level + 1);
+            let (z, src_depth, dst_depth) = if is_3d {
+                (0, src_extent.depth as i32, dst_extent.depth as i32)
+            } else {
+                (first_layer as i32, layers, layers)
+            };
+
+            let src_box = pipe_box {
+                x: 0,
+                y: 0,
+                z: z,
+                width: src_extent.width as i32,
+                height: src_extent.height as i32,
+                depth: src_depth,
+            };
+            let dst_box = pipe_box {
+                x: 0,
+                y: 0,
+                z: z,
+                width: dst_extent.width as i32,
+                height: dst_extent.height as i32,
+                depth: dst_depth,
+            };
+
+            self.blit_mip(
+                res,
+                level,
+                &src_box,
+                format,
+                res,
+                level + 1,
+                &dst_box,
+                format,
+                pipe_tex_filter::PIPE_TEX_FILTER_LINEAR,
+                PIPE_MASK_RGBA,
+            );
+        }
+    }
+
     fn resource_map(
         &self,
         res: &PipeResource,
@@ -206,12 +591,9 @@ impl PipeContext {
         res: &PipeResource,
         offset: i32,
         size: i32,
-        rw: RWFlags,
-        map_type: ResourceMapType,
+        flags: MapFlags,
     ) -> PipeTransfer {
-        let mut flags: pipe_map_flags = map_type.into();
-        flags |= rw.into();
-        self._buffer_map(res, offset, size, flags).unwrap()
+        self._buffer_map(res, offset, size, flags.into()).unwrap()
     }
 
     pub fn buffer_map_directly(
@@ -230,6 +612,121 @@ impl PipeContext {
         unsafe { self.pipe.as_ref().buffer_unmap.unwrap()(self.pipe.as_ptr(), tx) };
     }
 
+    /// Maps `res` for CPU access, coalescing with any other live logical map of the same
+    /// resource and transparently routing through a shadow buffer if it can't be mapped
+    /// directly. Returns the host pointer; pair with [`Self::unmap_buffer`] once done with it.
+    pub fn map_buffer(
+        &self,
+        res: &PipeResource,
+        offset: i32,
+        size: i32,
+        rw: RWFlags,
+    ) -> *mut c_void {
+        let mut transfers = self.mappings.transfers.lock().unwrap();
+        let key = res.pipe();
+
+        let ptr = if let Some(mapping) = transfers.get_mut(&key) {
+            mapping.pending += 1;
+            mapping.tx.ptr()
+        } else {
+            let mapping = match self.buffer_map_directly(res, offset, size, rw) {
+                Some(tx) => MappingTransfer {
+                    tx,
+                    shadow: None,
+                    offset,
+                    size,
+                    pending: 1,
+                },
+                None => {
+                    // can't map the real resource directly (e.g. it's tiled or only accessible
+                    // to the device); stage a linear shadow, copy the live contents into it and
+                    // map that instead.
+                    let shadow = self
+                        .screen
+                        .resource_create_buffer(size as u32, ResourceType::Staging)
+                        .expect("failed to allocate mapping shadow buffer");
+
+                    let bx = pipe_box {
+                        x: offset,
+                        width: size,
+                        height: 1,
+                        depth: 1,
+                        ..Default::default()
+                    };
+                    self.resource_copy_region(res, &shadow, &[0, 0, 0], &bx);
+
+                    let tx = self
+                        .buffer_map_directly(&shadow, 0, size, rw)
+                        .expect("a freshly created staging buffer must be directly mappable");
+
+                    MappingTransfer {
+                        tx,
+                        shadow: Some(shadow),
+                        offset,
+                        size,
+                        pending: 1,
+                    }
+                }
+            };
+
+            let ptr = mapping.tx.ptr();
+            transfers.insert(key, mapping);
+            ptr
+        };
+        drop(transfers);
+
+        *self
+            .mappings
+            .ptr_refs
+            .lock()
+            .unwrap()
+            .entry(ptr)
+            .or_insert(0) += 1;
+
+        ptr
+    }
+
+    /// Drops one logical reference to `res`'s current mapping. Once every reference taken out
+    /// by [`Self::map_buffer`] has been returned, the underlying transfer is torn down, writing
+    /// a shadow's contents back to `res` first if `write_back` is set.
+    pub fn unmap_buffer(&self, res: &PipeResource, write_back: bool) {
+        let mut transfers = self.mappings.transfers.lock().unwrap();
+        let key = res.pipe();
+
+        let Some(mapping) = transfers.get_mut(&key) else {
+            return;
+        };
+
+        let ptr = mapping.tx.ptr();
+        let mut ptr_refs = self.mappings.ptr_refs.lock().unwrap();
+        if let Some(count) = ptr_refs.get_mut(&ptr) {
+            *count -= 1;
+            if *count == 0 {
+                ptr_refs.remove(&ptr);
+            }
+        }
+        drop(ptr_refs);
+
+        mapping.pending -= 1;
+        if mapping.pending != 0 {
+            return;
+        }
+
+        let mapping = transfers.remove(&key).unwrap();
+        if write_back {
+            if let Some(shadow) = &mapping.shadow {
+                let bx = pipe_box {
+                    x: 0,
+                    width: mapping.size,
+                    height: 1,
+                    depth: 1,
+                    ..Default::default()
+                };
+                self.resource_copy_region(shadow, res, &[mapping.offset as u32, 0, 0], &bx);
+            }
+        }
+    }
+
     pub fn _texture_map(
         &self,
         res: &PipeResource,
@@ -239,16 +736,8 @@ impl PipeContext {
         self.resource_map(res, bx, flags, false)
     }
 
-    pub fn texture_map(
-        &self,
-        res: &PipeResource,
-        bx: &pipe_box,
-        rw: RWFlags,
-        map_type: ResourceMapType,
-    ) -> PipeTransfer {
-        let mut flags: pipe_map_flags = map_type.into();
-        flags |= rw.into();
-        self._texture_map(res, bx, flags).unwrap()
+    pub fn texture_map(&self, res: &PipeResource, bx: &pipe_box, flags: MapFlags) -> PipeTransfer {
+        self._texture_map(res, bx, flags.into()).unwrap()
     }
 
     pub fn texture_map_directly(
@@ -357,6 +846,31 @@ impl PipeContext {
         unsafe { self.pipe.as_ref().launch_grid.unwrap()(self.pipe.as_ptr(), &info) }
     }
 
+    /// Dispatches a grid whose dimensions are read from `indirect` (three consecutive `u32`s at
+    /// `indirect_offset`) rather than supplied by the CPU. Used to chain a kernel that computes
+    /// the next one's launch size without a flush-read-relaunch round trip back to the host.
+    pub fn launch_grid_indirect(
+        &self,
+        work_dim: u32,
+        block: [u32; 3],
+        indirect: &PipeResource,
+        indirect_offset: u32,
+    ) {
+        let info = pipe_grid_info {
+            pc: 0,
+            input: ptr::null(),
+            variable_shared_mem: 0,
+            work_dim: work_dim,
+            block: block,
+            last_block: [0; 3],
+            grid: [0; 3],
+            grid_base: [0; 3],
+            indirect: indirect.pipe(),
+            indirect_offset: indirect_offset,
+        };
+        unsafe { self.pipe.as_ref().launch_grid.unwrap()(self.pipe.as_ptr(), &info) }
+    }
+
     pub fn set_global_binding(&self, res: &[Arc<PipeResource>], out: &mut [*mut u32]) {
         let mut res: Vec<_> = res.iter().map(|r| r.pipe()).collect();
         unsafe {
@@ -466,8 +980,96 @@ impl PipeContext {
             PipeFence::new(fence, &self.screen)
         }
     }
+
+    /// Creates a query of `query_type` (e.g. `PIPE_QUERY_TIMESTAMP`), `index` selecting which of
+    /// several instances for query types that support more than one (e.g. per-vertex-stream
+    /// queries). Returns `None` if this context has no query support at all.
+    pub fn create_query(&self, query_type: c_uint, index: c_uint) -> Option<PipeQuery> {
+        if !self.has_queries {
+            return None;
+        }
+
+        let q = unsafe {
+            self.pipe.as_ref().create_query.unwrap()(self.pipe.as_ptr(), query_type, index)
+        };
+        NonNull::new(q).map(PipeQuery)
+    }
+
+    pub fn begin_query(&self, q: &PipeQuery) -> bool {
+        self.has_queries
+            && unsafe { self.pipe.as_ref().begin_query.unwrap()(self.pipe.as_ptr(), q.0.as_ptr()) }
+    }
+
+    pub fn end_query(&self, q: &PipeQuery) -> bool {
+        self.has_queries
+            && unsafe { self.pipe.as_ref().end_query.unwrap()(self.pipe.as_ptr(), q.0.as_ptr()) }
+    }
+
+    /// Reads back the result of `q` as a plain `u64`, blocking for it to become available if
+    /// `wait` is set. Returns `None` if the result isn't ready yet (only possible when `!wait`)
+    /// or this context has no query support.
+    pub fn get_query_result(&self, q: &PipeQuery, wait: bool) -> Option<u64> {
+        if !self.has_queries {
+            return None;
+        }
+
+        unsafe {
+            let mut result = pipe_query_result { u64_: 0 };
+            let ok = self.pipe.as_ref().get_query_result.unwrap()(
+                self.pipe.as_ptr(),
+                q.0.as_ptr(),
+                wait,
+                &mut result,
+            );
+            if ok {
+                Some(result.u64_)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Resolves `queries` in order into a host-readable buffer, blocking until every result is
+    /// available. This is the shape the OpenCL event layer needs to report
+    /// CL_PROFILING_COMMAND_QUEUED/SUBMIT/START/END: one `PipeQuery` per timestamp, resolved
+    /// together into a single `Vec<u64>` once the command has retired.
+    pub fn resolve_queries(&self, queries: &[PipeQuery]) -> Vec<u64> {
+        queries
+            .iter()
+            .map(|q| self.get_query_result(q, true).unwrap_or(0))
+            .collect()
+    }
+
+    /// Convenience wrapper creating and immediately ending a `PIPE_QUERY_TIMESTAMP` query --
+    /// unlike most query types it doesn't have a begin/end range, just a single point in time.
+    pub fn query_timestamp(&self) -> Option<PipeQuery> {
+        let q = self.create_query(pipe_query_type::PIPE_QUERY_TIMESTAMP.0 as c_uint, 0)?;
+        self.end_query(&q);
+        Some(q)
+    }
+
+    /// Creates a `PIPE_QUERY_PIPELINE_STATISTICS_SINGLE` query covering dispatch and invocation
+    /// counts for compute work, for drivers advertising
+    /// [`PipeScreen::supports_pipeline_stats`].
+    pub fn query_pipeline_stats(&self) -> Option<PipeQuery> {
+        self.create_query(
+            pipe_query_type::PIPE_QUERY_PIPELINE_STATISTICS_SINGLE.0 as c_uint,
+            0,
+        )
+    }
+
+    pub fn destroy_query(&self, q: PipeQuery) {
+        unsafe {
+            self.pipe.as_ref().destroy_query.unwrap()(self.pipe.as_ptr(), q.0.as_ptr());
+        }
+    }
 }
 
+/// An in-flight or resolved Gallium query (e.g. a timestamp or pipeline-statistics query),
+/// created/begun/ended/resolved through the [`PipeContext`] that owns it. Opaque beyond the raw
+/// `pipe_query` handle -- callers only ever pass it back into `PipeContext`'s query methods.
+pub struct PipeQuery(NonNull<pipe_query>);
+
 impl Drop for PipeContext {
     fn drop(&mut self) {
         unsafe {
@@ -482,6 +1084,7 @@ fn has_required_cbs(context: &pipe_context) -> bool {
     has_required_feature!(context, destroy)
         & has_required_feature!(context, bind_compute_state)
         & has_required_feature!(context, bind_sampler_states)
+        & has_required_feature!(context, blit)
         & has_required_feature!(context, buffer_map)
         & has_required_feature!(context, buffer_subdata)
         & has_required_feature!(context, buffer_unmap)
@@ -503,3 +1106,13 @@ fn has_required_cbs(context: &pipe_context) -> bool {
         & has_required_feature!(context, texture_subdata)
         & has_required_feature!(context, texture_unmap)
 }
+
+// Queries back GPU timestamps/pipeline statistics for profiling; unlike `has_required_cbs`,
+// missing any of these just turns profiling off instead of failing context creation.
+fn has_query_cbs(context: &pipe_context) -> bool {
+    has_required_feature!(context, create_query)
+        & has_required_feature!(context, begin_query)
+        & has_required_feature!(context, end_query)
+        & has_required_feature!(context, get_query_result)
+        & has_required_feature!(context, destroy_query)
+}
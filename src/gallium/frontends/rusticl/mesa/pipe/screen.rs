@@ -65,6 +65,20 @@ impl ComputeParam<Vec<u64>> for PipeScreen {
 pub enum ResourceType {
     Normal,
     Staging,
+    /// A resource whose contents are only zeroed lazily, on demand, via
+    /// `PipeContext::ensure_initialized` rather than up front. The template itself needs no
+    /// special handling -- the driver doesn't guarantee zeroed memory either way -- so tracking
+    /// lives entirely on the `PipeContext` side.
+    Cleared,
+}
+
+/// A winsys handle exported from [`PipeScreen::resource_export_handle`]: either a DMA-buf fd or
+/// a shared/KMS handle, depending on the `handle_type` it was requested with.
+pub struct Handle {
+    pub fd_or_handle: i32,
+    pub stride: u32,
+    pub offset: u32,
+    pub modifier: u64,
 }
 
 impl ResourceType {
@@ -75,7 +89,7 @@ impl ResourceType {
                 tmpl.flags |= PIPE_RESOURCE_FLAG_MAP_PERSISTENT | PIPE_RESOURCE_FLAG_MAP_COHERENT;
                 tmpl.bind |= PIPE_BIND_LINEAR;
             }
-            Self::Normal => {}
+            Self::Normal | Self::Cleared => {}
         }
     }
 }
@@ -123,6 +137,61 @@ impl PipeScreen {
         }
     }
 
+    /// Imports a winsys handle (a DMA-buf fd, or a shared/KMS handle) as a resource, for
+    /// zero-copy sharing of a buffer/image produced by another API (Vulkan/EGL/GL) or process.
+    /// Returns `None` if the screen doesn't implement `resource_from_handle` at all.
+    pub fn resource_import_handle(
+        &self,
+        tmpl: &pipe_resource,
+        handle_type: winsys_handle_type,
+        fd_or_handle: i32,
+        stride: u32,
+        offset: u32,
+    ) -> Option<PipeResource> {
+        unsafe {
+            let func = (*self.screen).resource_from_handle?;
+
+            let mut handle = winsys_handle {
+                type_: handle_type,
+                handle: fd_or_handle as u32,
+                stride,
+                offset,
+                ..Default::default()
+            };
+
+            PipeResource::new(func(self.screen, tmpl, &mut handle, 0), false)
+        }
+    }
+
+    /// Exports `res` as a winsys handle of `handle_type` (a DMA-buf fd, or a shared/KMS handle),
+    /// the counterpart to [`Self::resource_import_handle`]. Returns `None` if the screen doesn't
+    /// implement `resource_get_handle`, or the export itself fails.
+    pub fn resource_export_handle(
+        &self,
+        res: &PipeResource,
+        handle_type: winsys_handle_type,
+    ) -> Option<Handle> {
+        unsafe {
+            let func = (*self.screen).resource_get_handle?;
+
+            let mut handle = winsys_handle {
+                type_: handle_type,
+                ..Default::default()
+            };
+
+            if !func(self.screen, ptr::null_mut(), res.pipe(), &mut handle, 0) {
+                return None;
+            }
+
+            Some(Handle {
+                fd_or_handle: handle.handle as i32,
+                stride: handle.stride,
+                offset: handle.offset,
+                modifier: handle.modifier,
+            })
+        }
+    }
+
     pub fn resource_create_buffer(
         &self,
         size: u32,
@@ -159,6 +228,22 @@ impl PipeScreen {
         self.resource_create_from_user(&tmpl, mem)
     }
 
+    /// Binding flags a texture template should request, given what `(format, target)` actually
+    /// supports. `PIPE_BIND_SAMPLER_VIEW` is always requested -- every CL image needs to at least
+    /// be sampleable -- but `PIPE_BIND_SHADER_IMAGE` is only OR'd in when the driver reports
+    /// support for it, so a format that can merely be sampled doesn't fail (or silently fall
+    /// back) resource creation just because it can't also back a writable image.
+    fn texture_bind(&self, format: pipe_format, target: pipe_texture_target) -> u32 {
+        let mut bind = PIPE_BIND_SAMPLER_VIEW;
+        if self.is_format_supported(format, target, PIPE_BIND_SHADER_IMAGE) {
+            bind |= PIPE_BIND_SHADER_IMAGE;
+        }
+        bind
+    }
+
+    /// Returns the created resource together with the bind flags it was actually created with,
+    /// so callers can tell whether the image came out writable (`PIPE_BIND_SHADER_IMAGE` set) or
+    /// read-only-sampler-view-only.
     pub fn resource_create_texture(
         &self,
         width: u32,
@@ -168,8 +253,9 @@ impl PipeScreen {
         target: pipe_texture_target,
         format: pipe_format,
         res_type: ResourceType,
-    ) -> Option<PipeResource> {
+    ) -> Option<(PipeResource, u32)> {
         let mut tmpl = pipe_resource::default();
+        let bind = self.texture_bind(format, target);
 
         tmpl.set_target(target);
         tmpl.set_format(format);
@@ -177,11 +263,11 @@ impl PipeScreen {
         tmpl.height0 = height;
         tmpl.depth0 = depth;
         tmpl.array_size = array_size;
-        tmpl.bind = PIPE_BIND_SAMPLER_VIEW | PIPE_BIND_SHADER_IMAGE;
+        tmpl.bind = bind;
 
         res_type.apply(&mut tmpl);
 
-        self.resource_create(&tmpl)
+        self.resource_create(&tmpl).map(|res| (res, tmpl.bind))
     }
 
     pub fn resource_create_texture_from_user(
@@ -193,8 +279,9 @@ impl PipeScreen {
         target: pipe_texture_target,
         format: pipe_format,
         mem: *mut c_void,
-    ) -> Option<PipeResource> {
+    ) -> Option<(PipeResource, u32)> {
         let mut tmpl = pipe_resource::default();
+        let bind = self.texture_bind(format, target);
 
         tmpl.set_target(target);
         tmpl.set_format(format);
@@ -202,15 +289,32 @@ impl PipeScreen {
         tmpl.height0 = height;
         tmpl.depth0 = depth;
         tmpl.array_size = array_size;
-        tmpl.bind = PIPE_BIND_SAMPLER_VIEW | PIPE_BIND_SHADER_IMAGE;
+        tmpl.bind = bind;
 
         self.resource_create_from_user(&tmpl, mem)
+            .map(|res| (res, bind))
     }
 
     pub fn param(&self, cap: pipe_cap) -> i32 {
         unsafe { (*self.screen).get_param.unwrap()(self.screen, cap) }
     }
 
+    pub fn supports_timestamp_queries(&self) -> bool {
+        self.param(pipe_cap::PIPE_CAP_QUERY_TIMESTAMP) != 0
+    }
+
+    pub fn supports_pipeline_stats(&self) -> bool {
+        self.param(pipe_cap::PIPE_CAP_QUERY_PIPELINE_STATISTICS_SINGLE) != 0
+    }
+
+    /// Ticks per second for values returned by a `PIPE_QUERY_TIMESTAMP` query, derived from the
+    /// driver's nanosecond timer resolution so CL_PROFILING timestamps can be converted to a
+    /// common unit regardless of the underlying GPU clock.
+    pub fn timestamp_frequency(&self) -> u64 {
+        let resolution_ns = self.param(pipe_cap::PIPE_CAP_TIMER_RESOLUTION).max(1) as u64;
+        1_000_000_000 / resolution_ns
+    }
+
     pub fn shader_param(&self, t: pipe_shader_type, cap: pipe_shader_cap) -> i32 {
         unsafe { (*self.screen).get_shader_param.unwrap()(self.screen, t, cap) }
     }
@@ -311,11 +415,28 @@ impl PipeScreen {
     }
 
     pub(super) fn fence_finish(&self, fence: *mut pipe_fence_handle) {
+        self.fence_finish_timeout(fence, PIPE_TIMEOUT_INFINITE as u64);
+    }
+
+    /// Waits up to `timeout_ns` for `fence` to signal, returning whether it actually did. Passing
+    /// `PIPE_TIMEOUT_INFINITE` blocks forever like [`Self::fence_finish`]; passing `0` polls
+    /// without blocking (see [`Self::fence_is_signaled`]), letting callers implement a timed wait
+    /// or a "maintain"-style poll loop instead of blocking forever on every wait.
+    pub(super) fn fence_finish_timeout(
+        &self,
+        fence: *mut pipe_fence_handle,
+        timeout_ns: u64,
+    ) -> bool {
         unsafe {
             let s = &mut *self.screen;
-            s.fence_finish.unwrap()(s, ptr::null_mut(), fence, PIPE_TIMEOUT_INFINITE as u64);
+            s.fence_finish.unwrap()(s, ptr::null_mut(), fence, timeout_ns)
         }
     }
+
+    /// Non-blocking check for whether `fence` has already signaled.
+    pub(super) fn fence_is_signaled(&self, fence: *mut pipe_fence_handle) -> bool {
+        self.fence_finish_timeout(fence, 0)
+    }
 }
 
 impl Drop for PipeScreen {
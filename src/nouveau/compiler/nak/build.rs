@@ -0,0 +1,76 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+//! Generates the per-opcode enum-to-bit-value lookup tables `sm50.rs` needs (e.g. `MemScope`
+//! encoded one way for `MEMBAR` and another for `SULD`) from the declarative spec in
+//! `enum_tables.in`, rather than leaving each one a hand-written `match` at its call site. This
+//! is the same idea as holey-bytes' `instructions-template`/`build.rs` approach to generating
+//! instruction tables: one source of truth for the opcode-specific bit assignment, diffable
+//! independent of the encoder code that consumes it.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=enum_tables.in");
+
+    let spec = fs::read_to_string("enum_tables.in")
+        .expect("failed to read enum_tables.in");
+    let generated = generate(&spec);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("enum_tables.rs"), generated)
+        .expect("failed to write enum_tables.rs");
+}
+
+/// Turns the `table <name>: <Type> { Variant => value, ... }` blocks in `spec` into one
+/// `pub(crate) fn <name>(v: <Type>) -> u8` per block.
+fn generate(spec: &str) -> String {
+    let mut out = String::new();
+    let mut lines = spec.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let rest = line
+            .strip_prefix("table ")
+            .unwrap_or_else(|| panic!("expected `table <name>: <Type> {{`, got {line:?}"));
+        let (name, rest) = rest
+            .split_once(':')
+            .unwrap_or_else(|| panic!("missing `:` after table name in {line:?}"));
+        let enum_ty = rest.trim().trim_end_matches('{').trim();
+        let name = name.trim();
+
+        out.push_str(&format!(
+            "pub(crate) fn {name}(v: {enum_ty}) -> u8 {{\n    match v {{\n"
+        ));
+
+        for line in &mut lines {
+            let line = line.trim();
+            if line == "}" {
+                break;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (variant, value) = line
+                .trim_end_matches(',')
+                .split_once("=>")
+                .unwrap_or_else(|| panic!("expected `Variant => value`, got {line:?}"));
+            out.push_str(&format!(
+                "        {enum_ty}::{} => {},\n",
+                variant.trim(),
+                value.trim(),
+            ));
+        }
+
+        out.push_str("    }\n}\n\n");
+    }
+
+    out
+}
@@ -0,0 +1,347 @@
+// Copyright © 2024 Collabora, Ltd.
+// SPDX-License-Identifier: MIT
+
+use crate::ir::*;
+
+/// Number of hardware scoreboard barriers available for tracking variable-latency ops.
+/// `InstrDeps::wr_bar()`/`rd_bar()` use index 7 to mean "none", so only 0..6 are real.
+const NUM_BARRIERS: usize = 6;
+
+/// The `delay` field is 4 bits wide.
+const MAX_DELAY: u8 = 15;
+
+/// Conservative fixed issue-to-issue stall for a fixed-latency ALU op with no entry in
+/// `fixed_latency_for` below. Using this for everything is always correct, just not
+/// maximally throughput-optimal.
+const FIXED_LATENCY: u8 = 2;
+
+/// Per-opcode issue-to-issue latency for SM50's short ALU pipe (the "simple" encoders in
+/// `sm50.rs`: `F2F`/`I2F`/`I2I`/`MOV`/`SEL`/`PRMT`), which retire a cycle sooner than the
+/// `FIXED_LATENCY` fallback covers the rest of the ALU with.
+fn fixed_latency_for(op: &Op) -> u8 {
+    match op {
+        Op::F2F(_) | Op::I2F(_) | Op::I2I(_) | Op::Mov(_) | Op::Sel(_)
+        | Op::Prmt(_) => 1,
+        _ => FIXED_LATENCY,
+    }
+}
+
+/// The maximum number of source slots the operand reuse cache can track (`reuse_mask` is 4
+/// bits, one per slot).
+const NUM_REUSE_SLOTS: usize = 4;
+
+fn reg_of_dst(dst: &Dst) -> Option<RegRef> {
+    match dst {
+        Dst::Reg(r) => Some(*r),
+        _ => None,
+    }
+}
+
+fn reg_of_src(src: &Src) -> Option<RegRef> {
+    match src.src_ref {
+        SrcRef::Reg(r) => Some(r),
+        _ => None,
+    }
+}
+
+fn regs_overlap(a: RegRef, b: RegRef) -> bool {
+    a.file() == b.file()
+        && u32::from(a.base_idx()) < u32::from(b.base_idx()) + u32::from(b.comps())
+        && u32::from(b.base_idx()) < u32::from(a.base_idx()) + u32::from(a.comps())
+}
+
+/// True for ops whose result (or, for ops with no destination like stores, whose source
+/// operands) isn't available or safe to overwrite on a fixed schedule, and so needs a
+/// scoreboard barrier instead of a fixed `delay`.
+fn is_variable_latency(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::MuFu(_)
+            | Op::Ld(_)
+            | Op::Ldc(_)
+            | Op::St(_)
+            | Op::Atom(_)
+            | Op::ALd(_)
+            | Op::ASt(_)
+            | Op::SuLd(_)
+            | Op::SuSt(_)
+            | Op::SuAtom(_)
+            | Op::Tex(_)
+            | Op::Tld(_)
+            | Op::Tld4(_)
+            | Op::Tmml(_)
+            | Op::Txd(_)
+            | Op::Txq(_)
+            | Op::Shfl(_)
+            | Op::MemBar(_)
+    )
+}
+
+/// One in-flight variable-latency op, tracked by scoreboard barrier index: the registers it's
+/// protecting until the async unit signals completion (its destination for ops that allocate a
+/// write barrier, or its sources for ops like stores that have none to protect instead).
+struct PendingBarrier {
+    regs: Vec<RegRef>,
+}
+
+struct Scoreboard {
+    barriers: [Option<PendingBarrier>; NUM_BARRIERS],
+    /// Index to try first on the next `alloc`, advanced round-robin on every allocation
+    /// (whether it lands on a free slot or has to evict one) so barrier reuse is spread evenly
+    /// across all six instead of always hammering the lowest free index -- that would otherwise
+    /// let one barrier churn through every variable-latency op in a block while the rest sit
+    /// idle, which is no more correct but needlessly serializes things that didn't have to wait.
+    next: usize,
+}
+
+impl Scoreboard {
+    fn new() -> Self {
+        Self {
+            barriers: std::array::from_fn(|_| None),
+            next: 0,
+        }
+    }
+
+    /// Returns the bitmask of barriers that protect any of `regs` -- these must be waited on
+    /// before this instruction can safely read or write them -- and frees each one, since a
+    /// wait is exactly what lets the barrier be reused by a later op.
+    fn wait_mask_for(&mut self, regs: &[RegRef]) -> u8 {
+        let mut mask = 0_u8;
+        for (i, barrier) in self.barriers.iter_mut().enumerate() {
+            let Some(pending) = barrier else {
+                continue;
+            };
+            let hit = pending
+                .regs
+                .iter()
+                .any(|br| regs.iter().any(|r| regs_overlap(*br, *r)));
+            if hit {
+                mask |= 1 << i;
+                *barrier = None;
+            }
+        }
+        mask
+    }
+
+    /// Allocates a barrier to protect `regs`, returning its index and, when every barrier is
+    /// already in flight, the mask of the in-flight barrier that had to be waited on so it could
+    /// be reused (0 otherwise).
+    fn alloc(&mut self, regs: Vec<RegRef>) -> (u8, u8) {
+        let free = (0..NUM_BARRIERS)
+            .map(|off| (self.next + off) % NUM_BARRIERS)
+            .find(|&idx| self.barriers[idx].is_none());
+
+        let (idx, evicted) = match free {
+            Some(idx) => (idx, 0),
+            // All barriers are in flight. Evicting the one `next` points to forces a wait on
+            // it here, which is correct (just conservative) -- a block with enough concurrent
+            // variable-latency ops to exhaust all six barriers ends up partially serialized
+            // instead of over-allocating.
+            None => (self.next, 1 << self.next),
+        };
+
+        self.barriers[idx] = Some(PendingBarrier { regs });
+        self.next = (idx + 1) % NUM_BARRIERS;
+        (idx as u8, evicted)
+    }
+}
+
+/// How much the operand reuse cache flagging in [`calc_block_instr_deps`] helped: the number of
+/// same-slot register repeats it found (the bank conflicts the hardware would otherwise pay for)
+/// versus how many it was actually safe to flag for reuse. The two differ when the earlier
+/// instruction overwrites the very register it just fed the reuse cache, which invalidates the
+/// cached copy before the next instruction could read it back.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReuseCacheStats {
+    pub potential: u32,
+    pub flagged: u32,
+}
+
+impl ReuseCacheStats {
+    fn merge(&mut self, other: ReuseCacheStats) {
+        self.potential += other.potential;
+        self.flagged += other.flagged;
+    }
+}
+
+/// Per-instruction register occupancy of the `NUM_REUSE_SLOTS` operand slots, in the same order
+/// `Op::for_each_src` visits them -- slot 0 is the `8..16` source, slot 1 is `20..28`, and so on,
+/// mirroring how the two-source-form encoders in `sm50.rs` lay sources out.
+fn slot_regs(op: &Op) -> [Option<RegRef>; NUM_REUSE_SLOTS] {
+    let mut slots = [None; NUM_REUSE_SLOTS];
+    let mut slot = 0;
+    op.for_each_src(|src, _| {
+        if slot < NUM_REUSE_SLOTS {
+            slots[slot] = reg_of_src(src);
+        }
+        slot += 1;
+    });
+    slots
+}
+
+/// Models the Maxwell operand reuse cache: one physical latch per source slot, each holding
+/// whatever register the most recently issued instruction fetched into that slot. Setting the
+/// `.reuse` flag on an instruction tells the hardware to keep its slot latches alive for the next
+/// issue instead of re-fetching from the register bank, so a flag is only safe when the *next*
+/// instruction's same slot reads the identical register and this instruction didn't just
+/// overwrite that register itself (which would make the latched copy stale).
+fn calc_reuse_masks(
+    instrs: &[Box<Instr>],
+    dst_regs_per_instr: &[Vec<RegRef>],
+) -> (Vec<u8>, ReuseCacheStats) {
+    let mut stats = ReuseCacheStats::default();
+    let mut masks = vec![0_u8; instrs.len()];
+
+    if instrs.is_empty() {
+        return (masks, stats);
+    }
+
+    let slots: Vec<_> = instrs.iter().map(|i| slot_regs(&i.op)).collect();
+
+    for i in 0..instrs.len() - 1 {
+        let mut mask = 0_u8;
+        for slot in 0..NUM_REUSE_SLOTS {
+            let (Some(cur), Some(next)) = (slots[i][slot], slots[i + 1][slot]) else {
+                continue;
+            };
+            if cur != next {
+                continue;
+            }
+            stats.potential += 1;
+
+            let clobbered = dst_regs_per_instr[i]
+                .iter()
+                .any(|d| regs_overlap(*d, cur));
+            if clobbered {
+                continue;
+            }
+
+            mask |= 1 << slot;
+            stats.flagged += 1;
+        }
+        masks[i] = mask;
+    }
+
+    (masks, stats)
+}
+
+fn calc_block_instr_deps(instrs: &mut [Box<Instr>]) -> ReuseCacheStats {
+    let mut scoreboard = Scoreboard::new();
+    let mut dst_regs_per_instr = Vec::with_capacity(instrs.len());
+    let mut all_deps = Vec::with_capacity(instrs.len());
+
+    for i in 0..instrs.len() {
+        let mut src_regs = Vec::new();
+        instrs[i].op.for_each_src(|src, _| {
+            if let Some(r) = reg_of_src(src) {
+                src_regs.push(r);
+            }
+        });
+        let mut dst_regs = Vec::new();
+        instrs[i].op.for_each_dst(|dst, _| {
+            if let Some(r) = reg_of_dst(dst) {
+                dst_regs.push(r);
+            }
+        });
+
+        // RAW on our sources and WAR/WAW on our destinations: wait on any barrier protecting
+        // a register we're about to read or write.
+        let mut touched = src_regs.clone();
+        touched.extend(dst_regs.iter().copied());
+        let mut wt_bar_mask = scoreboard.wait_mask_for(&touched);
+
+        let mut wr_bar = None;
+        let mut rd_bar = None;
+        if is_variable_latency(&instrs[i].op) {
+            if !dst_regs.is_empty() {
+                let (bar, evicted) = scoreboard.alloc(dst_regs.clone());
+                wr_bar = Some(bar);
+                wt_bar_mask |= evicted;
+            } else if !src_regs.is_empty() {
+                // No destination (e.g. a store or MEMBAR): the barrier instead protects the
+                // source registers (address/data) until the async unit is done reading them,
+                // guarding against a later instruction overwriting them too soon.
+                let (bar, evicted) = scoreboard.alloc(src_regs.clone());
+                rd_bar = Some(bar);
+                wt_bar_mask |= evicted;
+            }
+        }
+
+        // Fixed-latency ops are covered by `delay`: stall the next issue long enough for this
+        // result to be visible if the very next instruction reads or overwrites it, otherwise
+        // issue back-to-back.
+        let delay = if wr_bar.is_none() && rd_bar.is_none() {
+            let mut hazard = false;
+            if let Some(next) = instrs.get(i + 1) {
+                next.op.for_each_src(|src, _| {
+                    if let Some(r) = reg_of_src(src) {
+                        hazard |= dst_regs.iter().any(|d| regs_overlap(*d, r));
+                    }
+                });
+                next.op.for_each_dst(|dst, _| {
+                    if let Some(r) = reg_of_dst(dst) {
+                        hazard |= dst_regs.iter().any(|d| regs_overlap(*d, r))
+                            || src_regs.iter().any(|s| regs_overlap(*s, r));
+                    }
+                });
+            }
+            if hazard {
+                fixed_latency_for(&instrs[i].op)
+            } else {
+                1
+            }
+        } else {
+            1
+        };
+
+        // A variable-latency wait is exactly the kind of long stall the hardware would rather
+        // switch warps for than sit idle on.
+        let yld = wt_bar_mask != 0;
+
+        let mut deps = InstrDeps::new();
+        deps.delay = delay.min(MAX_DELAY);
+        deps.yld = yld;
+        if let Some(bar) = wr_bar {
+            deps.set_wr_bar(bar);
+        }
+        if let Some(bar) = rd_bar {
+            deps.set_rd_bar(bar);
+        }
+        deps.wt_bar_mask = wt_bar_mask;
+
+        dst_regs_per_instr.push(dst_regs);
+        all_deps.push(deps);
+    }
+
+    // Operand reuse needs to look one instruction *ahead* (the flag is set on the earlier
+    // instruction to keep its slot latched for the next one), so it's computed in a second pass
+    // once every instruction's dst_regs are known rather than threaded through the loop above.
+    let (reuse_masks, stats) = calc_reuse_masks(instrs, &dst_regs_per_instr);
+    for ((instr, mut deps), reuse_mask) in
+        instrs.iter_mut().zip(all_deps).zip(reuse_masks)
+    {
+        deps.reuse_mask = reuse_mask;
+        instr.deps = deps;
+    }
+
+    stats
+}
+
+impl Shader<'_> {
+    /// Computes `InstrDeps` (stall counts, scoreboard barriers, and reuse hints) for every
+    /// instruction in every block, ready for the target's `set_instr_deps` to pack into its
+    /// control word. This only schedules dependencies for the existing instruction order; it
+    /// does not reorder instructions to improve throughput.
+    ///
+    /// Returns the operand reuse cache stats summed over every block, so callers that care about
+    /// the benefit of reuse flagging (e.g. a `-v` compiler stats dump) don't have to re-derive it.
+    pub fn calc_instr_deps(&mut self) -> ReuseCacheStats {
+        let mut stats = ReuseCacheStats::default();
+        for f in &mut self.functions {
+            for b in &mut f.blocks {
+                stats.merge(calc_block_instr_deps(&mut b.instrs));
+            }
+        }
+        stats
+    }
+}
@@ -195,6 +195,10 @@ impl<'a> TestShaderBuilder<'a> {
     }
 
     pub fn compile(mut self) -> Box<ShaderBin> {
+        self.compile_with_fp64(false)
+    }
+
+    pub fn compile_with_fp64(mut self, fp64: bool) -> Box<ShaderBin> {
         self.b.push_op(OpExit {});
         let block = BasicBlock {
             label: self.label,
@@ -224,7 +228,7 @@ impl<'a> TestShaderBuilder<'a> {
             slm_size: 0,
             uses_global_mem: true,
             writes_global_mem: true,
-            uses_fp64: false,
+            uses_fp64: fp64,
             stage: ShaderStageInfo::Compute(cs_info),
             io: ShaderIoInfo::None,
         };
@@ -280,30 +284,82 @@ fn test_sanity() {
     }
 }
 
-fn f32_eq(a: f32, b: f32) -> bool {
+// Maps IEEE-754 bit patterns onto a monotonically increasing integer key
+// so that adjacent floats (in value order, across the zero crossing) are
+// adjacent keys.  This lets us measure distance in ULPs by simple
+// integer subtraction instead of a fixed absolute epsilon, which is
+// simultaneously too loose for tiny magnitudes and too strict for huge
+// ones.
+fn f32_eq(a: f32, b: f32, ulp_tolerance: u32) -> bool {
     if a.is_nan() && b.is_nan() {
-        true
+        return true;
     } else if a.is_nan() || b.is_nan() {
         // If one is NaN but not the other, fail
-        false
-    } else {
-        (a - b).abs() < 0.000001
+        return false;
     }
+
+    fn key(bits: u32) -> i32 {
+        let i = bits as i32;
+        if i < 0 {
+            i32::MIN.wrapping_sub(i)
+        } else {
+            i
+        }
+    }
+
+    let dist = i64::from(key(a.to_bits())) - i64::from(key(b.to_bits()));
+    dist.unsigned_abs() <= u64::from(ulp_tolerance)
 }
 
-fn f64_eq(a: f64, b: f64) -> bool {
+fn f64_eq(a: f64, b: f64, ulp_tolerance: u32) -> bool {
     if a.is_nan() && b.is_nan() {
-        true
+        return true;
     } else if a.is_nan() || b.is_nan() {
         // If one is NaN but not the other, fail
-        false
-    } else {
-        (a - b).abs() < 0.000001
+        return false;
+    }
+
+    fn key(bits: u64) -> i64 {
+        let i = bits as i64;
+        if i < 0 {
+            i64::MIN.wrapping_sub(i)
+        } else {
+            i
+        }
+    }
+
+    let dist = i128::from(key(a.to_bits())) - i128::from(key(b.to_bits()));
+    dist.unsigned_abs() <= u128::from(ulp_tolerance)
+}
+
+/// The number of ULPs of slop to allow between the GPU result and the
+/// `fold` result for a given op.  Ops that are required to be correctly
+/// rounded or bit-exact (most of them) get `0`; transcendental
+/// approximations (MUFU, RRO) get a small, hardware-documented count.
+fn op_ulp_tolerance(op: &Op) -> u32 {
+    match op {
+        Op::MuFu(_) | Op::Rro(_) => 2,
+        _ => 0,
     }
 }
 
 pub fn test_foldable_op_with(
+    op: impl Foldable + Clone + Into<Op>,
+    rand_u32: impl FnMut(usize) -> u32,
+) {
+    // Square in the number of source components by default; see the
+    // comment below on `invocations` for why.
+    test_foldable_op_n(op, None, rand_u32)
+}
+
+/// Like [test_foldable_op_with] but runs exactly `invocations` test cases
+/// instead of the usual `src_comps * src_comps * 100`.  Passing
+/// `Some(1)` together with a `rand_u32` that replays a captured word
+/// tuple turns this into a one-shot regression test for a bug a fuzz
+/// run discovered; see [replay_foldable_op].
+pub fn test_foldable_op_n(
     mut op: impl Foldable + Clone + Into<Op>,
+    invocations: Option<usize>,
     mut rand_u32: impl FnMut(usize) -> u32,
 ) {
     let run = RunSingleton::get();
@@ -311,6 +367,7 @@ pub fn test_foldable_op_with(
 
     let mut comps = 0_u16;
     let mut fold_src = Vec::new();
+    let mut uses_fp64 = false;
     let src_types = op.src_types();
     for (i, src) in op.srcs_as_mut_slice().iter_mut().enumerate() {
         match src_types[i] {
@@ -328,7 +385,15 @@ pub fn test_foldable_op_with(
                 fold_src.push(FoldData::U32(0));
             }
             SrcType::F64 => {
-                todo!("Double ops aren't tested yet");
+                let lo = b.ld_test_data(comps * 4, MemType::B32)[0];
+                comps += 1;
+                let hi = b.ld_test_data(comps * 4, MemType::B32)[0];
+                comps += 1;
+                let data = SSARef::from([lo, hi]);
+
+                *src = data.into();
+                fold_src.push(FoldData::Vec2([0, 0]));
+                uses_fp64 = true;
             }
             SrcType::Pred => {
                 let data = b.ld_test_data(comps * 4, MemType::B32);
@@ -364,6 +429,7 @@ pub fn test_foldable_op_with(
             DstType::F64 => {
                 *dst = b.alloc_ssa(RegFile::GPR, 2).into();
                 fold_dst.push(FoldData::Vec2([0, 0]));
+                uses_fp64 = true;
             }
             typ => panic!("Can't auto-test {typ:?} data"),
         }
@@ -390,13 +456,14 @@ pub fn test_foldable_op_with(
     let comps = usize::from(comps); // Drop mutability
     let dst_comps = comps - src_comps;
 
-    let bin = b.compile();
+    let bin = b.compile_with_fp64(uses_fp64);
+    let ulp_tolerance = op_ulp_tolerance(&op.clone().into());
 
     // We're throwing random data at it here so the idea is that the number
     // of test cases we need to get good coverage is relative to the square
     // of the number of components.  For a big op like IAdd3X, this is going
     // to give us 2500 iterations.
-    let invocations = src_comps * src_comps * 100;
+    let invocations = invocations.unwrap_or(src_comps * src_comps * 100);
 
     let mut data = Vec::new();
     for _ in 0..invocations {
@@ -430,84 +497,300 @@ pub fn test_foldable_op_with(
     // Now, check the results
     for invoc_id in 0..invocations {
         let data = &data[(invoc_id * comps)..((invoc_id + 1) * comps)];
-
-        let mut c = 0_usize;
-        for src in &mut fold_src {
-            match src {
-                FoldData::Pred(b) => {
-                    let u = data[c];
-                    *b = (u & 1) != 0;
-                    c += 1;
-                }
-                FoldData::U32(u) => {
-                    *u = data[c];
-                    c += 1;
-                }
-                FoldData::Vec2(v) => {
-                    *v = [data[c + 0], data[c + 1]];
-                    c += 2;
-                }
-            }
-        }
-        debug_assert!(c == src_comps);
-
-        let mut fold = OpFoldData {
-            srcs: &fold_src,
-            dsts: &mut fold_dst,
-        };
-        op.fold(&*run.sm, &mut fold);
-
-        debug_assert!(fold_dst.len() == op.dsts_as_slice().len());
-        for (i, dst) in fold_dst.iter().enumerate() {
-            match dst {
-                FoldData::Pred(b) => {
-                    let d = data[c];
-                    c += 1;
-                    assert_eq!(*b, (d & 1) != 0);
-                }
-                FoldData::U32(u) => {
-                    let d = data[c];
-                    c += 1;
-
-                    match dst_types[i] {
-                        DstType::GPR => {
-                            assert_eq!(*u, d);
+        let src_words = &data[..src_comps];
+
+        let checked =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let mut c = 0_usize;
+                for src in &mut fold_src {
+                    match src {
+                        FoldData::Pred(b) => {
+                            let u = data[c];
+                            *b = (u & 1) != 0;
+                            c += 1;
+                        }
+                        FoldData::U32(u) => {
+                            *u = data[c];
+                            c += 1;
                         }
-                        DstType::F32 => {
-                            assert!(f32_eq(
-                                f32::from_bits(*u),
-                                f32::from_bits(d)
-                            ));
+                        FoldData::Vec2(v) => {
+                            *v = [data[c + 0], data[c + 1]];
+                            c += 2;
                         }
-                        typ => panic!("Can't auto-test {typ:?} data"),
                     }
                 }
-                FoldData::Vec2(v) => {
-                    let d = [data[c + 0], data[c + 1]];
-                    c += 2;
-
-                    match dst_types[i] {
-                        DstType::F64 => {
-                            let v_f64 = f64::from_bits(
-                                u64::from(v[0]) | (u64::from(v[1]) << 32),
-                            );
-                            let d_f64 = f64::from_bits(
-                                u64::from(d[0]) | (u64::from(d[1]) << 32),
-                            );
-                            assert!(f64_eq(v_f64, d_f64));
+                debug_assert!(c == src_comps);
+
+                let mut fold = OpFoldData {
+                    srcs: &fold_src,
+                    dsts: &mut fold_dst,
+                };
+                op.fold(&*run.sm, &mut fold);
+
+                debug_assert!(fold_dst.len() == op.dsts_as_slice().len());
+                for (i, dst) in fold_dst.iter().enumerate() {
+                    match dst {
+                        FoldData::Pred(b) => {
+                            let d = data[c];
+                            c += 1;
+                            assert_eq!(*b, (d & 1) != 0);
+                        }
+                        FoldData::U32(u) => {
+                            let d = data[c];
+                            c += 1;
+
+                            match dst_types[i] {
+                                DstType::GPR => {
+                                    assert_eq!(*u, d);
+                                }
+                                DstType::F32 => {
+                                    assert!(f32_eq(
+                                        f32::from_bits(*u),
+                                        f32::from_bits(d),
+                                        ulp_tolerance,
+                                    ));
+                                }
+                                typ => {
+                                    panic!("Can't auto-test {typ:?} data")
+                                }
+                            }
+                        }
+                        FoldData::Vec2(v) => {
+                            let d = [data[c + 0], data[c + 1]];
+                            c += 2;
+
+                            match dst_types[i] {
+                                DstType::F64 => {
+                                    let v_f64 = f64::from_bits(
+                                        u64::from(v[0])
+                                            | (u64::from(v[1]) << 32),
+                                    );
+                                    let d_f64 = f64::from_bits(
+                                        u64::from(d[0])
+                                            | (u64::from(d[1]) << 32),
+                                    );
+                                    assert!(f64_eq(
+                                        v_f64,
+                                        d_f64,
+                                        ulp_tolerance,
+                                    ));
+                                }
+                                typ => {
+                                    panic!("Can't auto-test {typ:?} data")
+                                }
+                            }
                         }
-                        typ => panic!("Can't auto-test {typ:?} data"),
                     }
                 }
-            }
+                debug_assert!(c == comps);
+            }));
+
+        if let Err(payload) = checked {
+            eprintln!(
+                "test_foldable_op_with: invoc_id {invoc_id} failed; \
+                 replay with replay_foldable_op(op, &{src_words:?})"
+            );
+            std::panic::resume_unwind(payload);
         }
-        debug_assert!(c == comps);
+    }
+}
+
+/// Re-runs a single, previously captured invocation of `op` against the
+/// exact `src_words` that triggered a mismatch, skipping all random
+/// generation.  This gives a one-liner regression test for any bug a
+/// fuzzing run of [test_foldable_op] or [test_foldable_op_edge_cases]
+/// discovers.
+pub fn replay_foldable_op(
+    op: impl Foldable + Clone + Into<Op>,
+    src_words: &[u32],
+) {
+    let src_words = src_words.to_vec();
+    let mut pos = 0_usize;
+    test_foldable_op_n(op, Some(1), move |_i| {
+        let w = src_words[pos];
+        pos += 1;
+        w
+    });
+}
+
+/// Picks the RNG seed for a fuzz run.  Honors `NAK_TEST_SEED` so a seed
+/// printed by a failing run can be pasted back in to reproduce it
+/// exactly; otherwise draws a fresh one.
+fn test_rng_seed() -> u64 {
+    match std::env::var("NAK_TEST_SEED") {
+        Ok(s) => u64::from_str(&s).unwrap(),
+        Err(_) => {
+            use std::time::{SystemTime, UNIX_EPOCH};
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as u64
+        }
+    }
+}
+
+/// Runs `body` with a fresh, seeded [Acorn] and, on any assertion
+/// failure, prints the seed that produced it before re-raising the
+/// panic so the failure can be reproduced with `NAK_TEST_SEED=<seed>`.
+fn with_seeded_acorn(body: impl FnOnce(&mut Acorn) + std::panic::UnwindSafe) {
+    let seed = test_rng_seed();
+    let mut a = Acorn::new_seeded(seed);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(
+        || body(&mut a),
+    ));
+    if let Err(payload) = result {
+        eprintln!(
+            "fuzz run failed; rerun with NAK_TEST_SEED={seed} to reproduce"
+        );
+        std::panic::resume_unwind(payload);
     }
 }
 
 pub fn test_foldable_op(op: impl Foldable + Clone + Into<Op>) {
-    let mut a = Acorn::new();
-    test_foldable_op_with(op, &mut |_| a.get_u32());
+    with_seeded_acorn(|a| test_foldable_op_with(op, &mut |_| a.get_u32()));
+}
+
+// Interesting F32 bit patterns: ±0.0, ±Inf, quiet/signaling NaN, the
+// smallest subnormal, 1.0, a couple of powers of two, and values just
+// above/below 1.0.
+const F32_SPECIALS: &[u32] = &[
+    0x0000_0000,
+    0x8000_0000,
+    0x7f80_0000,
+    0xff80_0000,
+    0x7fc0_0000,
+    0x7fa0_0000,
+    0x0000_0001,
+    0x3f80_0000,
+    0x4000_0000,
+    0x3f00_0000,
+    0x3f7f_ffff,
+    0x3f80_0001,
+];
+
+// Same idea as `F32_SPECIALS` but for the low/high words of an F64.
+const F64_SPECIALS: &[u64] = &[
+    0x0000_0000_0000_0000,
+    0x8000_0000_0000_0000,
+    0x7ff0_0000_0000_0000,
+    0xfff0_0000_0000_0000,
+    0x7ff8_0000_0000_0000,
+    0x7ff4_0000_0000_0000,
+    0x0000_0000_0000_0001,
+    0x3ff0_0000_0000_0000,
+    0x4000_0000_0000_0000,
+    0x3fe0_0000_0000_0000,
+    0x3fef_ffff_ffff_ffff,
+    0x3ff0_0000_0000_0001,
+];
+
+const I32_SPECIALS: &[u32] = &[
+    0x0000_0000,
+    0x0000_0001,
+    0xffff_ffff,
+    0x8000_0000,
+    0x7fff_ffff,
+];
+
+/// Like [test_foldable_op] but draws from a weighted pool of special
+/// values (signed zeros, infinities, NaNs, subnormals, integer extremes,
+/// ...) roughly 30% of the time instead of feeding every source with
+/// pure uniform random bits.  This hits the corner cases where folding
+/// bugs actually live, which uniform random inputs almost never reach.
+pub fn test_foldable_op_edge_cases(op: impl Foldable + Clone + Into<Op>) {
+    let src_types = op.src_types();
+
+    // F64 sources are read as two separate B32 words (low, then high),
+    // so stash the high word picked alongside a special low word until
+    // the next call for the same source index comes in.
+    let mut f64_hi: Vec<Option<u32>> = vec![None; src_types.len()];
+
+    with_seeded_acorn(|a| {
+        test_foldable_op_with(op, &mut |i| {
+            if let Some(hi) = f64_hi[i].take() {
+                return hi;
+            }
+
+            let special = a.get_u32() % 10 < 3;
+            match src_types[i] {
+                SrcType::F64 => {
+                    if special {
+                        let bits = F64_SPECIALS
+                            [(a.get_u32() as usize) % F64_SPECIALS.len()];
+                        f64_hi[i] = Some((bits >> 32) as u32);
+                        bits as u32
+                    } else {
+                        f64_hi[i] = Some(a.get_u32());
+                        a.get_u32()
+                    }
+                }
+                SrcType::F16 | SrcType::F16v2 | SrcType::F32 if special => {
+                    F32_SPECIALS[(a.get_u32() as usize) % F32_SPECIALS.len()]
+                }
+                SrcType::I32 | SrcType::B32 | SrcType::GPR | SrcType::ALU
+                    if special =>
+                {
+                    I32_SPECIALS[(a.get_u32() as usize) % I32_SPECIALS.len()]
+                }
+                _ => a.get_u32(),
+            }
+        });
+    });
+}
+
+// Bit patterns that sit right on the boundaries a conversion op has to
+// get right: tie-breaking values (`x.5`), magnitudes that straddle the
+// destination type's representable range, and the infinities.
+const CONVERSION_F32_SPECIALS: &[u32] = &[
+    0x3f00_0000, // 0.5
+    0xbf00_0000, // -0.5
+    0x3fc0_0000, // 1.5
+    0x4020_0000, // 2.5 (ties to even on either side)
+    0x3f40_0000, // 0.75
+    0xcf00_0000, // -2147483648.0 (i32::MIN, exactly representable)
+    0x4f00_0000, // 2147483648.0 (just past i32::MAX)
+    0x4f80_0000, // 4294967296.0 (just past u32::MAX)
+    0x7f80_0000, // +Inf
+    0xff80_0000, // -Inf
+];
+
+fn conversion_rand_u32(a: &mut Acorn) -> u32 {
+    if a.get_u32() % 10 < 4 {
+        CONVERSION_F32_SPECIALS
+            [(a.get_u32() as usize) % CONVERSION_F32_SPECIALS.len()]
+    } else {
+        a.get_u32()
+    }
+}
+
+/// Drives a conversion/repack op (F2I, I2F, F2F, I2I, ...) through the
+/// cross product of rounding modes and the saturate flag, constructing a
+/// fresh shader for each configuration.  `make_op` builds the op for one
+/// `(rnd_mode, saturate)` pair; ops with no saturate bit (e.g. F2F) are
+/// free to ignore that argument.  Inputs are biased toward values near
+/// integer/representable boundaries so that fold-vs-encoder disagreement
+/// at tie-breaking and clamp edges gets caught, not just the common
+/// case.
+pub fn test_conversion_op_rounding_sweep<ConvOp>(
+    mut make_op: impl FnMut(FRndMode, bool) -> ConvOp,
+) where
+    ConvOp: Foldable + Clone + Into<Op>,
+{
+    const RND_MODES: [FRndMode; 4] = [
+        FRndMode::NearestEven,
+        FRndMode::NegInf,
+        FRndMode::PosInf,
+        FRndMode::Zero,
+    ];
+
+    for rnd_mode in RND_MODES {
+        for saturate in [false, true] {
+            let op = make_op(rnd_mode, saturate);
+            with_seeded_acorn(|a| {
+                test_foldable_op_with(op, &mut |_| conversion_rand_u32(a));
+            });
+        }
+    }
 }
 
 #[test]
@@ -76,6 +76,10 @@ fn derive_as_slice(
         Ident::new(&format!("{func_prefix}s_as_mut_slice"), Span::call_site());
     let types_fn =
         Ident::new(&format!("{func_prefix}_types"), Span::call_site());
+    let field_names_fn = Ident::new(
+        &format!("{func_prefix}_field_names"),
+        Span::call_site(),
+    );
     let ty_attr = format!("{func_prefix}_type");
     let ty_type = Ident::new(&format!("{search_type}Type"), Span::call_site());
 
@@ -97,9 +101,12 @@ fn derive_as_slice(
             assert!(has_repr_c, "Struct must be declared #[repr(C)]");
 
             let mut first = None;
+            let mut last: Option<Ident> = None;
+            let mut last_count = 0_usize;
             let mut count = 0_usize;
             let mut found_last = false;
             let mut types = TokenStream2::new();
+            let mut names = TokenStream2::new();
 
             if let Fields::Named(named) = s.fields {
                 for f in named.named {
@@ -119,9 +126,14 @@ fn derive_as_slice(
                             quote! { #ty_type::DEFAULT, }
                         };
 
+                        let field_name = f.ident.clone().unwrap();
+                        let field_name_str = field_name.to_string();
+                        last = Some(field_name);
+                        last_count = ty_count;
                         first.get_or_insert(f.ident);
                         for _ in 0..ty_count {
                             types.extend(ty.clone());
+                            names.extend(quote! { #field_name_str, });
                         }
                         count += ty_count;
                     } else {
@@ -139,9 +151,41 @@ fn derive_as_slice(
             }
 
             if let Some(name) = first {
+                let last = last.unwrap();
+
+                // `SrcsAsSlice`/`DstsAsSlice` reinterpret the run of
+                // fields from `name` to `last` as a raw `[#elem_type;
+                // #count]` slice, which is only sound if they are
+                // contiguous with no padding in between.  Catch a
+                // miscompile (e.g. a differently-aligned field sneaking
+                // into the middle of the run) at build time rather than
+                // via a garbled slice at runtime.  `last` may itself be
+                // an array, so the run before it holds `count -
+                // last_count` elements.
+                let layout_assert = quote! {
+                    const _: () = assert!(
+                        ::std::mem::offset_of!(#ident, #last)
+                            - ::std::mem::offset_of!(#ident, #name)
+                            == (#count - #last_count) * ::std::mem::size_of::<#elem_type>(),
+                        concat!(
+                            stringify!(#ident),
+                            ": ",
+                            #search_type,
+                            " fields are not contiguous",
+                        ),
+                    );
+                };
+
                 quote! {
+                    #layout_assert
+
                     impl #trait_name for #ident {
                         fn #as_slice(&self) -> &[#elem_type] {
+                            debug_assert_eq!(
+                                ::std::mem::offset_of!(#ident, #last)
+                                    - ::std::mem::offset_of!(#ident, #name),
+                                (#count - #last_count) * ::std::mem::size_of::<#elem_type>(),
+                            );
                             unsafe {
                                 let first = &self.#name as *const #elem_type;
                                 std::slice::from_raw_parts(first, #count)
@@ -159,6 +203,11 @@ fn derive_as_slice(
                             static TYPES: [#ty_type; #count] = [#types];
                             TypeList::Array(&TYPES)
                         }
+
+                        fn #field_names_fn(&self) -> &'static [&'static str] {
+                            static NAMES: [&str; #count] = [#names];
+                            &NAMES
+                        }
                     }
                 }
             } else {
@@ -175,6 +224,10 @@ fn derive_as_slice(
                         fn #types_fn(&self) -> TypeList<#ty_type> {
                             TypeList::Uniform(#ty_type::DEFAULT)
                         }
+
+                        fn #field_names_fn(&self) -> &'static [&'static str] {
+                            &[]
+                        }
                     }
                 }
             }
@@ -184,6 +237,7 @@ fn derive_as_slice(
             let mut as_slice_cases = TokenStream2::new();
             let mut as_mut_slice_cases = TokenStream2::new();
             let mut types_cases = TokenStream2::new();
+            let mut field_names_cases = TokenStream2::new();
             let mut is_uniform_cases = TokenStream2::new();
             for v in e.variants {
                 let case = v.ident;
@@ -196,6 +250,9 @@ fn derive_as_slice(
                 types_cases.extend(quote! {
                     #ident::#case(x) => x.#types_fn(),
                 });
+                field_names_cases.extend(quote! {
+                    #ident::#case(x) => x.#field_names_fn(),
+                });
                 if search_type == "Dst" {
                     is_uniform_cases.extend(quote! {
                         #ident::#case(x) => x.is_uniform(),
@@ -232,6 +289,12 @@ fn derive_as_slice(
                             #types_cases
                         }
                     }
+
+                    fn #field_names_fn(&self) -> &'static [&'static str] {
+                        match self {
+                            #field_names_cases
+                        }
+                    }
                     #is_uniform_func
                 }
             }
@@ -241,6 +304,143 @@ fn derive_as_slice(
     }
 }
 
+// Like `derive_as_slice`, but instead of reinterpreting consecutive
+// fields as a raw slice (which requires `#[repr(C)]` and contiguous
+// layout), walks the declared fields one at a time and calls back for
+// every matching field, including each element of an array field.  This
+// works regardless of where the fields sit relative to any interleaved
+// immediates or modifiers.
+fn derive_fold(
+    input: TokenStream,
+    trait_name: &str,
+    func_prefix: &str,
+    search_type: &str,
+) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let trait_name = Ident::new(trait_name, Span::call_site());
+    let elem_type = Ident::new(search_type, Span::call_site());
+    let for_each =
+        Ident::new(&format!("for_each_{func_prefix}"), Span::call_site());
+    let for_each_mut = Ident::new(
+        &format!("for_each_{func_prefix}_mut"),
+        Span::call_site(),
+    );
+    let ty_attr = format!("{func_prefix}_type");
+    let ty_type = Ident::new(&format!("{search_type}Type"), Span::call_site());
+
+    match data {
+        Data::Struct(s) => {
+            let mut visits = TokenStream2::new();
+            let mut visits_mut = TokenStream2::new();
+
+            let Fields::Named(named) = s.fields else {
+                panic!("Fields are not named");
+            };
+
+            for f in named.named {
+                if count_type(&f.ty, search_type) == 0 {
+                    continue;
+                }
+
+                let name = f.ident.clone().unwrap();
+                let ty_expr = match get_type_attr(&f, &ty_attr) {
+                    Some(s) => {
+                        let s = syn::parse_str::<Ident>(&s).unwrap();
+                        quote! { #ty_type::#s }
+                    }
+                    None => quote! { #ty_type::DEFAULT },
+                };
+
+                if matches!(f.ty, syn::Type::Array(_)) {
+                    visits.extend(quote! {
+                        for x in self.#name.iter() {
+                            f(x, #ty_expr);
+                        }
+                    });
+                    visits_mut.extend(quote! {
+                        for x in self.#name.iter_mut() {
+                            f(x, #ty_expr);
+                        }
+                    });
+                } else {
+                    visits.extend(quote! {
+                        f(&self.#name, #ty_expr);
+                    });
+                    visits_mut.extend(quote! {
+                        f(&mut self.#name, #ty_expr);
+                    });
+                }
+            }
+
+            quote! {
+                impl #trait_name for #ident {
+                    fn #for_each(
+                        &self,
+                        mut f: impl FnMut(&#elem_type, #ty_type),
+                    ) {
+                        #visits
+                    }
+
+                    fn #for_each_mut(
+                        &mut self,
+                        mut f: impl FnMut(&mut #elem_type, #ty_type),
+                    ) {
+                        #visits_mut
+                    }
+                }
+            }
+            .into()
+        }
+        Data::Enum(e) => {
+            let mut cases = TokenStream2::new();
+            let mut cases_mut = TokenStream2::new();
+            for v in e.variants {
+                let case = v.ident;
+                cases.extend(quote! {
+                    #ident::#case(x) => x.#for_each(f),
+                });
+                cases_mut.extend(quote! {
+                    #ident::#case(x) => x.#for_each_mut(f),
+                });
+            }
+            quote! {
+                impl #trait_name for #ident {
+                    fn #for_each(
+                        &self,
+                        f: impl FnMut(&#elem_type, #ty_type),
+                    ) {
+                        match self {
+                            #cases
+                        }
+                    }
+
+                    fn #for_each_mut(
+                        &mut self,
+                        f: impl FnMut(&mut #elem_type, #ty_type),
+                    ) {
+                        match self {
+                            #cases_mut
+                        }
+                    }
+                }
+            }
+            .into()
+        }
+        _ => panic!("Not a struct or enum type"),
+    }
+}
+
+#[proc_macro_derive(FoldSrcs, attributes(src_type))]
+pub fn derive_fold_srcs(input: TokenStream) -> TokenStream {
+    derive_fold(input, "FoldSrcs", "src", "Src")
+}
+
+#[proc_macro_derive(FoldDsts, attributes(dst_type))]
+pub fn derive_fold_dsts(input: TokenStream) -> TokenStream {
+    derive_fold(input, "FoldDsts", "dst", "Dst")
+}
+
 #[proc_macro_derive(SrcsAsSlice, attributes(src_type))]
 pub fn derive_srcs_as_slice(input: TokenStream) -> TokenStream {
     derive_as_slice(input, "SrcsAsSlice", "src", "Src")
@@ -251,40 +451,168 @@ pub fn derive_dsts_as_slice(input: TokenStream) -> TokenStream {
     derive_as_slice(input, "DstsAsSlice", "dst", "Dst")
 }
 
-#[proc_macro_derive(DisplayOp)]
-pub fn enum_derive_display_op(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+fn display_attr(attrs: &[Attribute]) -> Option<String> {
+    for attr in attrs {
+        if attr.path().is_ident("display") {
+            let Meta::List(ml) = &attr.meta else {
+                panic!("Expected #[display(\"...\")]");
+            };
+            let lit: LitStr = syn::parse2(ml.tokens.clone())
+                .expect("Expected #[display(\"...\")] to hold a string");
+            return Some(lit.value());
+        }
+    }
+    None
+}
 
-    if let Data::Enum(e) = data {
-        let mut fmt_dsts_cases = TokenStream2::new();
-        let mut fmt_op_cases = TokenStream2::new();
-        for v in e.variants {
-            let case = v.ident;
-            fmt_dsts_cases.extend(quote! {
-                #ident::#case(x) => x.fmt_dsts(f),
-            });
-            fmt_op_cases.extend(quote! {
-                #ident::#case(x) => x.fmt_op(f),
-            });
+// Parses a `#[display("...")]` template into a format string (with `{{`
+// and `}}` kept as escaped literal braces and each `{ ... }` group
+// rewritten to a bare `{}` or `{:spec}` placeholder) plus the list of
+// field-access expressions those placeholders refer to, in order.
+fn parse_display_template(template: &str) -> (String, Vec<TokenStream2>) {
+    let mut out = String::new();
+    let mut args = Vec::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push_str("}}");
+            }
+            '{' => {
+                let mut field = String::new();
+                let mut index = None;
+                let mut spec = None;
+                loop {
+                    match chars.next() {
+                        Some('[') => {
+                            let mut s = String::new();
+                            loop {
+                                match chars.next() {
+                                    Some(']') => break,
+                                    Some(c) => s.push(c),
+                                    None => panic!(
+                                        "Unterminated '[' in display template"
+                                    ),
+                                }
+                            }
+                            index = Some(s);
+                        }
+                        Some(':') => {
+                            let mut s = String::new();
+                            loop {
+                                match chars.next() {
+                                    Some('}') => break,
+                                    Some(c) => s.push(c),
+                                    None => panic!(
+                                        "Unterminated '{{' in display template"
+                                    ),
+                                }
+                            }
+                            spec = Some(s);
+                            break;
+                        }
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            panic!("Unterminated '{{' in display template")
+                        }
+                    }
+                }
+
+                let field = Ident::new(&field, Span::call_site());
+                let expr = if let Some(index) = index {
+                    let index: Expr = syn::parse_str(&index)
+                        .expect("Expected an index expression");
+                    quote! { self.#field[#index] }
+                } else {
+                    quote! { self.#field }
+                };
+                args.push(expr);
+
+                match spec {
+                    Some(spec) => out.push_str(&format!("{{:{spec}}}")),
+                    None => out.push_str("{}"),
+                }
+            }
+            '}' => panic!("Unmatched '}}' in display template"),
+            c => out.push(c),
         }
-        quote! {
-            impl DisplayOp for #ident {
-                fn fmt_dsts(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                    match self {
-                        #fmt_dsts_cases
+    }
+
+    (out, args)
+}
+
+/// For an enum, dispatches `fmt_dsts`/`fmt_op` to the inner op of
+/// whichever variant is active, same as before.
+///
+/// For a struct (or enum variant carrying one), a `#[display("...")]`
+/// attribute synthesizes `fmt_op` from a template: text is copied
+/// verbatim except that `{{`/`}}` decode to literal braces and a
+/// `{ field[index]:spec }` group names a field, optionally indexed,
+/// optionally with a format spec forwarded to Rust's formatting
+/// mini-language. `fmt_dsts` is left to the existing per-variant
+/// behavior, so only the opcode body is generated.  Without the
+/// attribute, a struct still needs a manual `impl DisplayOp`.
+#[proc_macro_derive(DisplayOp, attributes(display))]
+pub fn enum_derive_display_op(input: TokenStream) -> TokenStream {
+    let DeriveInput {
+        ident, data, attrs, ..
+    } = parse_macro_input!(input);
+
+    match data {
+        Data::Enum(e) => {
+            let mut fmt_dsts_cases = TokenStream2::new();
+            let mut fmt_op_cases = TokenStream2::new();
+            for v in e.variants {
+                let case = v.ident;
+                fmt_dsts_cases.extend(quote! {
+                    #ident::#case(x) => x.fmt_dsts(f),
+                });
+                fmt_op_cases.extend(quote! {
+                    #ident::#case(x) => x.fmt_op(f),
+                });
+            }
+            quote! {
+                impl DisplayOp for #ident {
+                    fn fmt_dsts(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        match self {
+                            #fmt_dsts_cases
+                        }
+                    }
+
+                    fn fmt_op(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        match self {
+                            #fmt_op_cases
+                        }
                     }
                 }
+            }
+            .into()
+        }
+        Data::Struct(_) => {
+            let template = display_attr(&attrs).expect(
+                "Structs deriving DisplayOp need a #[display(\"...\")] \
+                 attribute, or a manual impl",
+            );
+            let (fmt_str, args) = parse_display_template(&template);
+            let fmt_str = LitStr::new(&fmt_str, Span::call_site());
 
-                fn fmt_op(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                    match self {
-                        #fmt_op_cases
+            quote! {
+                impl DisplayOp for #ident {
+                    fn fmt_op(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, #fmt_str #(, #args)*)
                     }
                 }
             }
+            .into()
         }
-        .into()
-    } else {
-        panic!("Not an enum type");
+        _ => panic!("Not a struct or enum type"),
     }
 }
 
@@ -317,3 +645,162 @@ pub fn derive_from_variants(input: TokenStream) -> TokenStream {
 
     impls.into()
 }
+
+fn to_snake_case(camel: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in camel.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+enum NewFieldInit {
+    Arg,
+    Default,
+    Value(Expr),
+}
+
+fn new_field_init(field: &Field) -> NewFieldInit {
+    for attr in &field.attrs {
+        let Meta::List(ml) = &attr.meta else {
+            continue;
+        };
+        if !ml.path.is_ident("new") {
+            continue;
+        }
+
+        let tokens = format!("{}", ml.tokens);
+        let tokens = tokens.trim();
+        if tokens == "default" {
+            return NewFieldInit::Default;
+        } else if let Some(rest) = tokens.strip_prefix("value") {
+            let rest = rest.trim().trim_start_matches('=').trim();
+            let lit: LitStr =
+                syn::parse_str(rest).expect("Expected a string literal");
+            let expr: Expr = lit
+                .parse()
+                .expect("Expected #[new(value = \"expr\")] to hold an expr");
+            return NewFieldInit::Value(expr);
+        } else {
+            panic!("Unknown #[new(...)] attribute: {tokens}");
+        }
+    }
+    NewFieldInit::Arg
+}
+
+/// Derives an inherent `fn new(...) -> Self` taking one argument per
+/// field, in declaration order.  A field tagged `#[new(default)]` is
+/// dropped from the argument list and filled with `Default::default()`;
+/// one tagged `#[new(value = "expr")]` is instead initialized from the
+/// given expression.  This keeps `OpFoo::new(...)` call sites stable as
+/// optional fields are added, the same way `derive-new` does.
+#[proc_macro_derive(NewOp, attributes(new))]
+pub fn derive_new_op(input: TokenStream) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let s = match data {
+        Data::Struct(s) => s,
+        _ => panic!("NewOp can only be derived for structs"),
+    };
+
+    let fields = match s.fields {
+        Fields::Named(named) => named.named,
+        _ => panic!("NewOp requires named fields"),
+    };
+
+    let mut params = TokenStream2::new();
+    let mut inits = TokenStream2::new();
+
+    for f in &fields {
+        let name = f.ident.clone().unwrap();
+        let ty = &f.ty;
+
+        match new_field_init(f) {
+            NewFieldInit::Arg => {
+                params.extend(quote! { #name: #ty, });
+                inits.extend(quote! { #name, });
+            }
+            NewFieldInit::Default => {
+                inits.extend(quote! { #name: Default::default(), });
+            }
+            NewFieldInit::Value(expr) => {
+                inits.extend(quote! { #name: #expr, });
+            }
+        }
+    }
+
+    quote! {
+        impl #ident {
+            pub fn new(#params) -> Self {
+                Self { #inits }
+            }
+        }
+    }
+    .into()
+}
+
+/// Derives, for every single-field tuple variant `Foo(OpFoo)`, an
+/// `is_foo()`, `as_foo()`, and `as_foo_mut()` accessor so callers don't
+/// have to write a `match`/`if let` to test or extract one op variant.
+#[proc_macro_derive(OpVariants)]
+pub fn derive_op_variants(input: TokenStream) -> TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let e = match data {
+        Data::Enum(e) => e,
+        _ => panic!("OpVariants can only be derived for enums"),
+    };
+
+    let mut methods = TokenStream2::new();
+    for v in e.variants {
+        let var_ident = v.ident;
+        let field_ty = match v.fields {
+            Fields::Unnamed(FieldsUnnamed { unnamed, .. })
+                if unnamed.len() == 1 =>
+            {
+                unnamed.into_iter().next().unwrap().ty
+            }
+            _ => panic!("Expected a single-field tuple variant Foo(OpFoo)"),
+        };
+
+        let snake = to_snake_case(&var_ident.to_string());
+        let is_fn = Ident::new(&format!("is_{snake}"), Span::call_site());
+        let as_fn = Ident::new(&format!("as_{snake}"), Span::call_site());
+        let as_mut_fn =
+            Ident::new(&format!("as_{snake}_mut"), Span::call_site());
+
+        methods.extend(quote! {
+            pub fn #is_fn(&self) -> bool {
+                matches!(self, #ident::#var_ident(_))
+            }
+
+            pub fn #as_fn(&self) -> Option<&#field_ty> {
+                match self {
+                    #ident::#var_ident(op) => Some(op),
+                    _ => None,
+                }
+            }
+
+            pub fn #as_mut_fn(&mut self) -> Option<&mut #field_ty> {
+                match self {
+                    #ident::#var_ident(op) => Some(op),
+                    _ => None,
+                }
+            }
+        });
+    }
+
+    quote! {
+        impl #ident {
+            #methods
+        }
+    }
+    .into()
+}
@@ -10,14 +10,161 @@ use bitview::*;
 use std::collections::HashMap;
 use std::ops::Range;
 
+// Per-opcode enum-to-bit-value tables generated by `build.rs` from `enum_tables.in` (e.g.
+// `membar_scope`/`suld_scope` below), rather than hand-written at each call site.
+include!(concat!(env!("OUT_DIR"), "/enum_tables.rs"));
+
 pub struct ShaderModel50 {
     sm: u8,
+    /// First-generation Maxwell (SM50) runs double-precision almost
+    /// entirely in software on the shader cores already, so the hardware
+    /// `OpD*` ops are themselves vanishingly slow there; route them through
+    /// `lower_f64_*` instead so at least the instruction count is ours to
+    /// control.
+    emulate_fp64: bool,
 }
 
 impl ShaderModel50 {
     pub fn new(sm: u8) -> Self {
         assert!(sm >= 50 && sm < 70);
-        Self { sm }
+        Self {
+            sm,
+            emulate_fp64: sm < 52,
+        }
+    }
+
+    /// Checks whether `op` can be encoded for this SM version, without attempting to encode it.
+    /// `encode_instr` calls this before handing `op` to [`SM50Op::encode`], so a combination that
+    /// would otherwise only be discovered via a panic mid-encode (say, a global `F64` atomic) is
+    /// instead reported up front and a caller can choose a different lowering.
+    pub fn supports(&self, op: &Op) -> Result<(), UnsupportedOp> {
+        match op {
+            Op::MuFu(mufu) => self.supports_mufu(mufu),
+            Op::Atom(atom) => self.supports_atom(atom),
+            Op::Out(out) => self.supports_out(out),
+            _ => Ok(()),
+        }
+    }
+
+    fn supports_mufu(&self, mufu: &OpMuFu) -> Result<(), UnsupportedOp> {
+        match mufu.op {
+            MuFuOp::Sqrt if self.sm >= 52 => Ok(()),
+            MuFuOp::Sqrt => {
+                Err(UnsupportedOp::new("MUFU.SQRT", "requires SM52+", self.sm))
+            }
+            MuFuOp::Tanh => Err(UnsupportedOp::new("MUFU.TANH", "", self.sm)),
+            _ => Ok(()),
+        }
+    }
+
+    fn supports_atom(&self, atom: &OpAtom) -> Result<(), UnsupportedOp> {
+        match atom.mem_space {
+            MemSpace::Global(_) => match atom.atom_type {
+                AtomType::U32
+                | AtomType::I32
+                | AtomType::U64
+                | AtomType::F32
+                | AtomType::I64 => Ok(()),
+                other => Err(UnsupportedOp::new(
+                    "ATOMG",
+                    format!("atom_type {other}"),
+                    self.sm,
+                )),
+            },
+            MemSpace::Local => Err(UnsupportedOp::new(
+                "ATOM",
+                "mem_space Local",
+                self.sm,
+            )),
+            MemSpace::Shared => match atom.atom_type {
+                AtomType::U32
+                | AtomType::I32
+                | AtomType::U64
+                | AtomType::I64 => Ok(()),
+                other => Err(UnsupportedOp::new(
+                    "ATOMS",
+                    format!("atom_type {other}"),
+                    self.sm,
+                )),
+            },
+        }
+    }
+
+    fn supports_out(&self, out: &OpOut) -> Result<(), UnsupportedOp> {
+        match &out.stream.src_ref {
+            SrcRef::Imm32(_) | SrcRef::CBuf(_) | SrcRef::Zero | SrcRef::Reg(_) => {
+                Ok(())
+            }
+            src => Err(UnsupportedOp::new("OUT", format!("src {src}"), self.sm)),
+        }
+    }
+}
+
+/// An [`Op`] (or one of its fields) that [`ShaderModel50::supports`] rejected: named well enough
+/// -- op, offending field, SM version -- that a caller can either fall back to a different
+/// lowering or surface a useful diagnostic instead of hitting a panic mid-encode.
+#[derive(Debug, Clone)]
+pub struct UnsupportedOp {
+    op: &'static str,
+    field: String,
+    sm: u8,
+}
+
+impl UnsupportedOp {
+    fn new(op: &'static str, field: impl Into<String>, sm: u8) -> Self {
+        Self {
+            op,
+            field: field.into(),
+            sm,
+        }
+    }
+}
+
+impl std::fmt::Display for UnsupportedOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{} is not supported on SM{}", self.op, self.sm)
+        } else {
+            write!(
+                f,
+                "{} with {} is not supported on SM{}",
+                self.op, self.field, self.sm,
+            )
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedOp {}
+
+/// Either way `try_encode_sm50_shader` can fail: an op that can't be represented on this SM at
+/// all ([`UnsupportedOp`]), or one that can but whose branch target doesn't fit the encoded field
+/// ([`BranchRangeError`]).
+#[derive(Debug, Clone)]
+pub enum EncodeError {
+    Unsupported(UnsupportedOp),
+    BranchRange(BranchRangeError),
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::Unsupported(e) => e.fmt(f),
+            EncodeError::BranchRange(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl From<UnsupportedOp> for EncodeError {
+    fn from(e: UnsupportedOp) -> Self {
+        EncodeError::Unsupported(e)
+    }
+}
+
+impl From<BranchRangeError> for EncodeError {
+    fn from(e: BranchRangeError) -> Self {
+        EncodeError::BranchRange(e)
     }
 }
 
@@ -43,6 +190,19 @@ impl ShaderModel for ShaderModel50 {
     }
 
     fn legalize_op(&self, b: &mut LegalizeBuilder, op: &mut Op) {
+        if let Op::MuFu(mufu) = op {
+            if let Some(lowered) = mufu.lower_sm50(self, b) {
+                *op = lowered;
+            }
+        }
+        if self.emulate_fp64 {
+            if let Some(lowered) = lower_f64_op(op, b) {
+                *op = lowered;
+            }
+        }
+        if let Some(lowered) = lower_strong_mem_order(op, b) {
+            *op = lowered;
+        }
         as_sm50_op_mut(op).legalize(b);
     }
 
@@ -68,8 +228,55 @@ struct SM50Encoder<'a> {
     labels: &'a HashMap<Label, usize>,
     inst: [u32; 2],
     sched: u32,
+    relocs: Vec<PendingReloc>,
+}
+
+/// What a [`PendingReloc`] computes from `target_ip`/`inst_ip` once both are known.
+#[derive(Clone, Copy)]
+enum RelocKind {
+    /// `target_ip - inst_ip - 8`, written as a signed field.
+    Rel32,
+}
+
+/// A branch-target fixup recorded during `encode` instead of being resolved inline: at the point
+/// an op calls `set_rel_offset`, the target block's IP is already known (it's precomputed before
+/// any encoding starts), but writing the offset eagerly would mean every such write duplicates the
+/// same range-check-or-silently-truncate logic. Deferring to a single `resolve_relocations` pass
+/// after every instruction's final position in the output buffer is known -- MachBuffer-style --
+/// keeps that validation in one place.
+struct PendingReloc {
+    /// Index into the final encoded word buffer of this instruction's low word. Filled in by
+    /// `encode_sm50_shader` once it knows where this instruction lands; zero while still attached
+    /// to the `SM50Encoder` that recorded it.
+    word_offset: usize,
+    range: Range<usize>,
+    kind: RelocKind,
+    label: Label,
+    inst_ip: usize,
+}
+
+/// A branch displacement that doesn't fit its encoded field even after `encode_sm50_shader`
+/// tried routing it through a single-hop trampoline. Named after the op/field/IPs involved so a
+/// caller can decide whether to fall back to a different lowering rather than just aborting.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchRangeError {
+    inst_ip: usize,
+    target_ip: usize,
+    bits: usize,
+}
+
+impl std::fmt::Display for BranchRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "branch from ip {} to ip {} does not fit in a {}-bit field, even via a trampoline",
+            self.inst_ip, self.target_ip, self.bits,
+        )
+    }
 }
 
+impl std::error::Error for BranchRangeError {}
+
 impl BitViewable for SM50Encoder<'_> {
     fn bits(&self) -> usize {
         BitView::new(&self.inst).bits()
@@ -280,6 +487,691 @@ impl SM50Encoder<'_> {
     }
 }
 
+//
+// Decoding
+//
+// Mirrors `SM50Encoder` bit-for-bit: every `get_*` helper here undoes exactly one `set_*` helper
+// above, so the two stay easy to keep in sync when an encoding changes. Only a handful of ops
+// have a `decode` counterpart so far -- enough to golden-file-verify their encoders and to back a
+// text disassembler -- with the rest dispatched through `decode_sm50_instr`'s fallback panic until
+// they grow one too.
+
+struct SM50Decoder<'a> {
+    inst: &'a [u32; 2],
+}
+
+impl BitViewable for SM50Decoder<'_> {
+    fn bits(&self) -> usize {
+        BitView::new(self.inst).bits()
+    }
+
+    fn get_bit_range_u64(&self, range: Range<usize>) -> u64 {
+        BitView::new(self.inst).get_bit_range_u64(range)
+    }
+}
+
+impl SM50Decoder<'_> {
+    fn opcode(&self) -> u16 {
+        self.get_field(48..64)
+    }
+
+    fn get_pred_reg(&self, range: Range<usize>) -> RegRef {
+        assert!(range.len() == 3);
+        RegRef::new(RegFile::Pred, self.get_field(range), 1)
+    }
+
+    fn get_pred(&self) -> Pred {
+        let reg = self.get_pred_reg(16..19);
+        let pred_inv = self.get_bit(19);
+        if reg.base_idx() == 7 {
+            Pred {
+                pred_ref: PredRef::None,
+                pred_inv,
+            }
+        } else {
+            Pred {
+                pred_ref: PredRef::Reg(reg),
+                pred_inv,
+            }
+        }
+    }
+
+    fn get_reg(&self, range: Range<usize>) -> RegRef {
+        assert!(range.len() == 8);
+        RegRef::new(RegFile::GPR, self.get_field(range), 1)
+    }
+
+    fn get_reg_src_ref(&self, range: Range<usize>) -> SrcRef {
+        let reg = self.get_reg(range);
+        if reg.base_idx() == 255 {
+            SrcRef::Zero
+        } else {
+            SrcRef::Reg(reg)
+        }
+    }
+
+    fn get_reg_fmod_src(
+        &self,
+        range: Range<usize>,
+        abs_bit: usize,
+        neg_bit: usize,
+    ) -> Src {
+        let mut src = Src::from(self.get_reg_src_ref(range));
+        if self.get_bit(abs_bit) {
+            src = src.fabs();
+        }
+        if self.get_bit(neg_bit) {
+            src = src.fneg();
+        }
+        src
+    }
+
+    fn get_reg_ineg_src(&self, range: Range<usize>, neg_bit: usize) -> Src {
+        let mut src = Src::from(self.get_reg_src_ref(range));
+        if self.get_bit(neg_bit) {
+            src = src.ineg();
+        }
+        src
+    }
+
+    fn get_dst(&self) -> Dst {
+        let reg = self.get_reg(0..8);
+        if reg.base_idx() == 255 {
+            Dst::None
+        } else {
+            Dst::Reg(reg)
+        }
+    }
+
+    fn get_src_imm32(&self, range: Range<usize>) -> u32 {
+        assert!(range.len() == 32);
+        self.get_field(range)
+    }
+
+    /// Undoes `SM50Encoder::set_src_imm_i20`: the 19-bit field plus `sign_bit` is a 20-bit two's
+    /// complement value, so sign-extend it back to a full `i32` by replicating the sign bit into
+    /// the top 12 bits.
+    fn get_src_imm_i20(&self, range: Range<usize>, sign_bit: usize) -> u32 {
+        assert!(range.len() == 19);
+        let field: u32 = self.get_field(range);
+        let sign: u32 = self.get_field(sign_bit..sign_bit + 1);
+        field | (sign * 0xfff80000)
+    }
+
+    /// Undoes `SM50Encoder::set_src_imm_f20`: the 19-bit field holds bits 12..31 of an `f32`, with
+    /// `sign_bit` holding bit 31 and the low 12 mantissa bits always zero.
+    fn get_src_imm_f20(&self, range: Range<usize>, sign_bit: usize) -> u32 {
+        assert!(range.len() == 19);
+        let field: u32 = self.get_field(range);
+        let sign: u32 = self.get_field(sign_bit..sign_bit + 1);
+        (field << 12) | (sign << 31)
+    }
+
+    fn get_src_cb(&self, range: Range<usize>) -> CBufRef {
+        let v = BitView::new_subset(self, range);
+        let offset: u32 = v.get_field(0..14);
+        let idx: u32 = v.get_field(14..19);
+        CBufRef {
+            buf: CBuf::Binding(idx),
+            offset: offset << 2,
+        }
+    }
+
+    fn get_cb_fmod_src(
+        &self,
+        range: Range<usize>,
+        abs_bit: usize,
+        neg_bit: usize,
+    ) -> Src {
+        let mut src = Src::from(SrcRef::CBuf(self.get_src_cb(range)));
+        if self.get_bit(abs_bit) {
+            src = src.fabs();
+        }
+        if self.get_bit(neg_bit) {
+            src = src.fneg();
+        }
+        src
+    }
+
+    fn get_cb_ineg_src(&self, range: Range<usize>, neg_bit: usize) -> Src {
+        let mut src = Src::from(SrcRef::CBuf(self.get_src_cb(range)));
+        if self.get_bit(neg_bit) {
+            src = src.ineg();
+        }
+        src
+    }
+
+    fn get_rnd_mode(&self, range: Range<usize>) -> FRndMode {
+        assert!(range.len() == 2);
+        match self.get_field::<u8>(range) {
+            0 => FRndMode::NearestEven,
+            1 => FRndMode::NegInf,
+            2 => FRndMode::PosInf,
+            3 => FRndMode::Zero,
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_pred_dst(&self, range: Range<usize>) -> Dst {
+        let reg = self.get_pred_reg(range);
+        if reg.base_idx() == 7 {
+            Dst::None
+        } else {
+            Dst::Reg(reg)
+        }
+    }
+
+    fn get_pred_src(&self, range: Range<usize>, not_bit: usize) -> Src {
+        let reg = self.get_pred_reg(range);
+        let not = self.get_bit(not_bit);
+        if reg.base_idx() == 7 {
+            Src::from(if not { SrcRef::False } else { SrcRef::True })
+        } else if not {
+            Src::from(SrcRef::Reg(reg)).bnot()
+        } else {
+            Src::from(SrcRef::Reg(reg))
+        }
+    }
+
+    fn get_float_cmp_op(&self, range: Range<usize>) -> FloatCmpOp {
+        assert!(range.len() == 4);
+        match self.get_field::<u8>(range) {
+            0x01 => FloatCmpOp::OrdLt,
+            0x02 => FloatCmpOp::OrdEq,
+            0x03 => FloatCmpOp::OrdLe,
+            0x04 => FloatCmpOp::OrdGt,
+            0x05 => FloatCmpOp::OrdNe,
+            0x06 => FloatCmpOp::OrdGe,
+            0x09 => FloatCmpOp::UnordLt,
+            0x0a => FloatCmpOp::UnordEq,
+            0x0b => FloatCmpOp::UnordLe,
+            0x0c => FloatCmpOp::UnordGt,
+            0x0d => FloatCmpOp::UnordNe,
+            0x0e => FloatCmpOp::UnordGe,
+            0x07 => FloatCmpOp::IsNum,
+            0x08 => FloatCmpOp::IsNan,
+            op => panic!("Unknown float cmp op {op:#x}"),
+        }
+    }
+
+    fn get_pred_set_op(&self, range: Range<usize>) -> PredSetOp {
+        assert!(range.len() == 2);
+        match self.get_field::<u8>(range) {
+            0 => PredSetOp::And,
+            1 => PredSetOp::Or,
+            2 => PredSetOp::Xor,
+            op => panic!("Unknown pred set op {op:#x}"),
+        }
+    }
+}
+
+/// Bit layout of the `.reg`/`.imm`/`.cbuf` source-form triple shared by most two-source float ALU
+/// ops: one opcode per form, `src1` always starting at bit 20 (8 bits wide for a register, 19 for
+/// an immediate or constant-buffer reference), and a register-negate/abs-negate pair of bits for
+/// each of the `.reg` and `.cbuf` forms (kept separate since a handful of ops, like FSET, actually
+/// put them in different places for each form). This is the authoritative description of the
+/// layout: both `encode` and `decode` below drive off it instead of repeating the match-and-
+/// set_field dance by hand in every op's own `encode`/`decode` function.
+struct FSrc1Forms {
+    reg_op: u16,
+    imm_op: u16,
+    cbuf_op: u16,
+    reg_range: Range<usize>,
+    imm_cbuf_range: Range<usize>,
+    reg_abs_bit: usize,
+    reg_neg_bit: usize,
+    cbuf_abs_bit: usize,
+    cbuf_neg_bit: usize,
+    sign_bit: usize,
+}
+
+impl FSrc1Forms {
+    fn encode(&self, e: &mut SM50Encoder<'_>, src: Src) {
+        match &src.src_ref {
+            SrcRef::Zero | SrcRef::Reg(_) => {
+                e.set_opcode(self.reg_op);
+                e.set_reg_fmod_src(
+                    self.reg_range.clone(),
+                    self.reg_abs_bit,
+                    self.reg_neg_bit,
+                    src,
+                );
+            }
+            SrcRef::Imm32(imm) => {
+                e.set_opcode(self.imm_op);
+                e.set_src_imm_f20(self.imm_cbuf_range.clone(), self.sign_bit, *imm);
+                assert!(src.src_mod.is_none());
+            }
+            SrcRef::CBuf(_) => {
+                e.set_opcode(self.cbuf_op);
+                e.set_cb_fmod_src(
+                    self.imm_cbuf_range.clone(),
+                    self.cbuf_abs_bit,
+                    self.cbuf_neg_bit,
+                    src,
+                );
+            }
+            src => panic!("Unsupported src type: {src}"),
+        }
+    }
+
+    fn decode(&self, d: &SM50Decoder<'_>) -> Src {
+        let opcode = d.opcode();
+        if opcode == self.reg_op {
+            d.get_reg_fmod_src(self.reg_range.clone(), self.reg_abs_bit, self.reg_neg_bit)
+        } else if opcode == self.imm_op {
+            Src::from(SrcRef::Imm32(
+                d.get_src_imm_f20(self.imm_cbuf_range.clone(), self.sign_bit),
+            ))
+        } else if opcode == self.cbuf_op {
+            d.get_cb_fmod_src(
+                self.imm_cbuf_range.clone(),
+                self.cbuf_abs_bit,
+                self.cbuf_neg_bit,
+            )
+        } else {
+            panic!("opcode {opcode:#06x} doesn't match any form in this table")
+        }
+    }
+
+    fn matches(&self, opcode: u16) -> bool {
+        opcode == self.reg_op || opcode == self.imm_op || opcode == self.cbuf_op
+    }
+}
+
+const FADD_SRC1_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5c58,
+    imm_op: 0x3858,
+    cbuf_op: 0x4c58,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 49,
+    reg_neg_bit: 45,
+    cbuf_abs_bit: 49,
+    cbuf_neg_bit: 45,
+    sign_bit: 56,
+};
+
+const DADD_SRC1_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5c70,
+    imm_op: 0x3870,
+    cbuf_op: 0x4c70,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 49,
+    reg_neg_bit: 45,
+    cbuf_abs_bit: 49,
+    cbuf_neg_bit: 45,
+    sign_bit: 56,
+};
+
+const FMNMX_SRC1_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5c60,
+    imm_op: 0x3860,
+    cbuf_op: 0x4c60,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 49,
+    reg_neg_bit: 45,
+    cbuf_abs_bit: 49,
+    cbuf_neg_bit: 45,
+    sign_bit: 56,
+};
+
+const RRO_SRC_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5c90,
+    imm_op: 0x3890,
+    cbuf_op: 0x4c90,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 49,
+    reg_neg_bit: 45,
+    cbuf_abs_bit: 49,
+    cbuf_neg_bit: 45,
+    sign_bit: 56,
+};
+
+// FSET's register and constant-buffer forms disagree on where the negate bit lives (53 vs. 6) --
+// a genuine hardware quirk, now visible as data instead of two divergent hand-written matches.
+const FSET_SRC1_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5800,
+    imm_op: 0x3000,
+    cbuf_op: 0x4800,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 44,
+    reg_neg_bit: 53,
+    cbuf_abs_bit: 44,
+    cbuf_neg_bit: 6,
+    sign_bit: 56,
+};
+
+const FSETP_SRC1_FORMS: FSrc1Forms = FSrc1Forms {
+    reg_op: 0x5bb0,
+    imm_op: 0x36b0,
+    cbuf_op: 0x4bb0,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    reg_abs_bit: 44,
+    reg_neg_bit: 6,
+    cbuf_abs_bit: 44,
+    cbuf_neg_bit: 6,
+    sign_bit: 56,
+};
+
+/// Same source-form triple as [`FSrc1Forms`], but for the ops (like FMUL's non-fast-immediate
+/// path) that read `src1` as a plain register/immediate/constant-buffer reference with no
+/// abs/negate modifiers of its own.
+struct FSrc1PlainForms {
+    reg_op: u16,
+    imm_op: u16,
+    cbuf_op: u16,
+    reg_range: Range<usize>,
+    imm_cbuf_range: Range<usize>,
+    sign_bit: usize,
+}
+
+impl FSrc1PlainForms {
+    fn encode(&self, e: &mut SM50Encoder<'_>, src: Src) {
+        assert!(src.src_mod.is_none());
+        match &src.src_ref {
+            SrcRef::Zero | SrcRef::Reg(_) => {
+                e.set_opcode(self.reg_op);
+                e.set_reg_src(self.reg_range.clone(), src);
+            }
+            SrcRef::Imm32(imm) => {
+                e.set_opcode(self.imm_op);
+                e.set_src_imm_f20(self.imm_cbuf_range.clone(), self.sign_bit, *imm);
+            }
+            SrcRef::CBuf(cbuf) => {
+                e.set_opcode(self.cbuf_op);
+                e.set_src_cb(self.imm_cbuf_range.clone(), cbuf);
+            }
+            src => panic!("Unsupported src type: {src}"),
+        }
+    }
+
+    fn decode(&self, d: &SM50Decoder<'_>) -> Src {
+        let opcode = d.opcode();
+        if opcode == self.reg_op {
+            Src::from(d.get_reg_src_ref(self.reg_range.clone()))
+        } else if opcode == self.imm_op {
+            Src::from(SrcRef::Imm32(
+                d.get_src_imm_f20(self.imm_cbuf_range.clone(), self.sign_bit),
+            ))
+        } else if opcode == self.cbuf_op {
+            Src::from(SrcRef::CBuf(d.get_src_cb(self.imm_cbuf_range.clone())))
+        } else {
+            panic!("opcode {opcode:#06x} doesn't match any form in this table")
+        }
+    }
+
+    fn matches(&self, opcode: u16) -> bool {
+        opcode == self.reg_op || opcode == self.imm_op || opcode == self.cbuf_op
+    }
+}
+
+const FMUL_SRC1_FORMS: FSrc1PlainForms = FSrc1PlainForms {
+    reg_op: 0x5c68,
+    imm_op: 0x3868,
+    cbuf_op: 0x4c68,
+    reg_range: 20..28,
+    imm_cbuf_range: 20..39,
+    sign_bit: 56,
+};
+
+fn decode_dadd(d: &SM50Decoder<'_>) -> Op {
+    Op::DAdd(OpDAdd {
+        dst: d.get_dst(),
+        srcs: [d.get_reg_fmod_src(8..16, 46, 48), DADD_SRC1_FORMS.decode(d)],
+        rnd_mode: d.get_rnd_mode(39..41),
+    })
+}
+
+fn decode_fadd(d: &SM50Decoder<'_>) -> Op {
+    let op = if d.opcode() == 0x0800 {
+        let imm32 = d.get_src_imm32(20..52);
+        OpFAdd {
+            dst: d.get_dst(),
+            srcs: [
+                d.get_reg_fmod_src(8..16, 54, 56),
+                Src::from(SrcRef::Imm32(imm32)),
+            ],
+            saturate: false,
+            rnd_mode: FRndMode::NearestEven,
+            ftz: d.get_bit(55),
+        }
+    } else {
+        OpFAdd {
+            dst: d.get_dst(),
+            srcs: [d.get_reg_fmod_src(8..16, 46, 48), FADD_SRC1_FORMS.decode(d)],
+            saturate: d.get_bit(50),
+            rnd_mode: d.get_rnd_mode(39..41),
+            ftz: d.get_bit(44),
+        }
+    };
+    Op::FAdd(op)
+}
+
+fn decode_fmnmx(d: &SM50Decoder<'_>) -> Op {
+    Op::FMnMx(OpFMnMx {
+        dst: d.get_dst(),
+        srcs: [d.get_reg_fmod_src(8..16, 46, 48), FMNMX_SRC1_FORMS.decode(d)],
+        min: d.get_pred_src(39..42, 42),
+        ftz: d.get_bit(44),
+    })
+}
+
+fn decode_rro(d: &SM50Decoder<'_>) -> Op {
+    Op::Rro(OpRro {
+        dst: d.get_dst(),
+        src: RRO_SRC_FORMS.decode(d),
+        op: match d.get_field::<u8>(39..40) {
+            0 => RroOp::SinCos,
+            1 => RroOp::Exp2,
+            _ => unreachable!(),
+        },
+    })
+}
+
+fn decode_fset(d: &SM50Decoder<'_>) -> Op {
+    Op::FSet(OpFSet {
+        dst: d.get_dst(),
+        srcs: [d.get_reg_fmod_src(8..16, 54, 43), FSET_SRC1_FORMS.decode(d)],
+        cmp_op: d.get_float_cmp_op(48..52),
+        ftz: d.get_bit(55),
+    })
+}
+
+fn decode_fsetp(d: &SM50Decoder<'_>) -> Op {
+    Op::FSetP(OpFSetP {
+        dst: d.get_pred_dst(3..6),
+        srcs: [d.get_reg_fmod_src(8..16, 7, 43), FSETP_SRC1_FORMS.decode(d)],
+        accum: d.get_pred_src(39..42, 42),
+        set_op: d.get_pred_set_op(45..47),
+        ftz: d.get_bit(47),
+        cmp_op: d.get_float_cmp_op(48..52),
+    })
+}
+
+fn decode_fmul(d: &SM50Decoder<'_>) -> Op {
+    let op = if d.opcode() == 0x1e00 {
+        let imm32 = d.get_src_imm32(20..52);
+        let src0 = d.get_reg_fmod_src(8..16, 46, 48);
+        let fneg = d.get_bit(19);
+        OpFMul {
+            dst: d.get_dst(),
+            srcs: [
+                src0,
+                if fneg {
+                    Src::from(SrcRef::Imm32(imm32)).fneg()
+                } else {
+                    Src::from(SrcRef::Imm32(imm32))
+                },
+            ],
+            saturate: d.get_bit(55),
+            rnd_mode: FRndMode::NearestEven,
+            ftz: d.get_bit(53),
+            dnz: d.get_bit(54),
+        }
+    } else {
+        // `encode` also ORs bit 48 with `srcs[0].fneg ^ srcs[1].fneg` before
+        // `set_reg_fmod_src` writes src0's own fneg into that same bit last, so the xor never
+        // actually survives into the final word -- bit 48 always ends up meaning src0's fneg,
+        // and this non-fast-immediate form has no way to negate src1 at all.
+        OpFMul {
+            dst: d.get_dst(),
+            srcs: [d.get_reg_fmod_src(8..16, 46, 48), FMUL_SRC1_FORMS.decode(d)],
+            saturate: d.get_bit(50),
+            rnd_mode: d.get_rnd_mode(39..41),
+            ftz: d.get_bit(44),
+            dnz: d.get_bit(45),
+        }
+    };
+    Op::FMul(op)
+}
+
+fn decode_iadd2(d: &SM50Decoder<'_>) -> Op {
+    let carry_in_src = |has_carry: bool| {
+        if has_carry {
+            Src::from(SrcRef::Reg(RegRef::zero(RegFile::Carry, 1)))
+        } else {
+            Src::from(SrcRef::Zero)
+        }
+    };
+    let carry_out_dst = |has_carry: bool| {
+        if has_carry {
+            Dst::Reg(RegRef::zero(RegFile::Carry, 1))
+        } else {
+            Dst::None
+        }
+    };
+
+    let op = match d.opcode() {
+        0x1c00 => OpIAdd2 {
+            dst: d.get_dst(),
+            srcs: [
+                d.get_reg_ineg_src(8..16, 56),
+                Src::from(SrcRef::Imm32(d.get_src_imm32(20..52))),
+            ],
+            carry_in: carry_in_src(d.get_bit(53)),
+            carry_out: carry_out_dst(d.get_bit(52)),
+        },
+        0x5c10 | 0x3810 | 0x4c10 => {
+            let src1 = match d.opcode() {
+                0x5c10 => d.get_reg_ineg_src(20..28, 48),
+                0x3810 => Src::from(SrcRef::Imm32(d.get_src_imm_i20(20..39, 56))),
+                0x4c10 => d.get_cb_ineg_src(20..39, 48),
+                _ => unreachable!(),
+            };
+            OpIAdd2 {
+                dst: d.get_dst(),
+                srcs: [d.get_reg_ineg_src(8..16, 49), src1],
+                carry_in: carry_in_src(d.get_bit(43)),
+                carry_out: carry_out_dst(d.get_bit(47)),
+            }
+        }
+        _ => panic!("Not an IADD2 opcode"),
+    };
+    Op::IAdd2(op)
+}
+
+/// Undoes `OpMov::encode`'s three `.reg`/`.imm`/`.cbuf` forms. Kept as a one-off rather than going
+/// through `FSrc1Forms` since MOV's quad-lane mask lives at a different bit range per form (the
+/// same kind of drift `FSrc1Forms` exists to prevent for the two-source float ops).
+fn decode_mov(d: &SM50Decoder<'_>) -> Op {
+    let op = match d.opcode() {
+        0x5c98 => OpMov {
+            dst: d.get_dst(),
+            src: Src::from(d.get_reg_src_ref(20..28)),
+            quad_lanes: d.get_field(39..43),
+        },
+        0x0100 => OpMov {
+            dst: d.get_dst(),
+            src: Src::from(SrcRef::Imm32(d.get_src_imm32(20..52))),
+            quad_lanes: d.get_field(12..16),
+        },
+        0x4c98 => OpMov {
+            dst: d.get_dst(),
+            src: Src::from(SrcRef::CBuf(d.get_src_cb(20..39))),
+            quad_lanes: d.get_field(39..43),
+        },
+        op => panic!("Not a MOV opcode: {op:#06x}"),
+    };
+    Op::Mov(op)
+}
+
+/// Undoes `OpMemBar::encode`.
+fn decode_membar(d: &SM50Decoder<'_>) -> Op {
+    Op::MemBar(OpMemBar {
+        scope: match d.get_field::<u8>(8..10) {
+            0 => MemScope::CTA,
+            1 => MemScope::GPU,
+            2 => MemScope::System,
+            scope => panic!("Unknown MEMBAR scope {scope:#x}"),
+        },
+    })
+}
+
+/// Undoes `OpCS2R::encode`.
+fn decode_cs2r(d: &SM50Decoder<'_>) -> Op {
+    Op::CS2R(OpCS2R {
+        dst: d.get_dst(),
+        idx: d.get_field(20..28),
+    })
+}
+
+/// Decodes a single two-word SM50 instruction back into its `Op`, the reverse of
+/// `as_sm50_op(op).encode(e)`. Only the opcodes handled by the `decode_*` functions below are
+/// recognized so far; every other opcode falls through to the panic below until it grows its own
+/// `decode_*` counterpart.
+fn decode_sm50_instr(inst: &[u32; 2]) -> Op {
+    let d = SM50Decoder { inst };
+    let op = d.opcode();
+    if op == 0x0800 || FADD_SRC1_FORMS.matches(op) {
+        decode_fadd(&d)
+    } else if FMNMX_SRC1_FORMS.matches(op) {
+        decode_fmnmx(&d)
+    } else if op == 0x1e00 || FMUL_SRC1_FORMS.matches(op) {
+        decode_fmul(&d)
+    } else if RRO_SRC_FORMS.matches(op) {
+        decode_rro(&d)
+    } else if FSET_SRC1_FORMS.matches(op) {
+        decode_fset(&d)
+    } else if FSETP_SRC1_FORMS.matches(op) {
+        decode_fsetp(&d)
+    } else if op == 0x1c00 || op == 0x5c10 || op == 0x3810 || op == 0x4c10 {
+        decode_iadd2(&d)
+    } else if DADD_SRC1_FORMS.matches(op) {
+        decode_dadd(&d)
+    } else if op == 0x5c98 || op == 0x0100 || op == 0x4c98 {
+        decode_mov(&d)
+    } else if op == 0xef98 {
+        decode_membar(&d)
+    } else if op == 0x50c8 {
+        decode_cs2r(&d)
+    } else {
+        panic!("Unsupported opcode for decode: {op:#06x}")
+    }
+}
+
+/// Reconstructs the `Op` sequence encoded by `encode_sm50_shader`, skipping the schedule word that
+/// precedes each group of three instructions. This is the round-trip counterpart used to
+/// golden-file-verify the encoder and to back a text disassembly mode.
+pub fn decode_sm50_shader(encoded: &[u32]) -> Vec<Op> {
+    assert!(encoded.len() % 8 == 0);
+
+    let mut ops = Vec::new();
+    for group in encoded.chunks_exact(8) {
+        // group[0..2] is the schedule word for this group of three instructions.
+        for instr in group[2..8].chunks_exact(2) {
+            let inst: [u32; 2] = [instr[0], instr[1]];
+            ops.push(decode_sm50_instr(&inst));
+        }
+    }
+    ops
+}
+
 //
 // Legalization helpers
 //
@@ -312,6 +1204,24 @@ pub trait SM50LegalizeBuildHelpers: LegalizeBuildHelpers {
             self.copy_alu_src(src, reg_file, src_type);
         }
     }
+
+    /// Re-materializes `src` through a fresh copy if it's already a register
+    /// pair but its low half isn't even-aligned. The F64 ops read 64-bit
+    /// operands as a register and its successor, so a misaligned incoming
+    /// allocation has to be fixed up here rather than caught only at encode
+    /// time.
+    fn copy_alu_src_if_misaligned_pair(
+        &mut self,
+        src: &mut Src,
+        reg_file: RegFile,
+        src_type: SrcType,
+    ) {
+        if let SrcRef::Reg(reg) = src.src_ref {
+            if reg.comps() > 1 && reg.base_idx() % 2 != 0 {
+                self.copy_alu_src(src, reg_file, src_type);
+            }
+        }
+    }
 }
 
 impl SM50LegalizeBuildHelpers for LegalizeBuilder<'_> {}
@@ -349,6 +1259,79 @@ fn legalize_ext_instr(op: &mut impl SrcsAsSlice, _b: &mut LegalizeBuilder) {
     }
 }
 
+/// `SM50Encoder::set_mem_order` is a no-op -- SM50 has no inline order/scope bits the way
+/// SM70+ does -- so a `MemOrder::Strong` access needs an explicit `MEMBAR` instead to get any
+/// ordering guarantee at all. Mirrors the acquire/release placement convention other Mesa
+/// backends use: the barrier runs before an acquire load (nothing after it in program order may
+/// be scheduled ahead of the barrier) and after a release store or atomic (the write isn't
+/// allowed to become visible until the barrier has drained everything before it). `Weak`/
+/// `Constant` accesses are untouched -- they didn't ask for cross-invocation ordering.
+fn lower_strong_mem_order(op: &mut Op, b: &mut LegalizeBuilder) -> Option<Op> {
+    // `true` for a fence-after (release) op, `false` for fence-before (acquire).
+    let scope = match op {
+        Op::Ld(ld) => match ld.access.order {
+            MemOrder::Strong(scope) => Some((scope, false)),
+            _ => None,
+        },
+        Op::St(st) => match st.access.order {
+            MemOrder::Strong(scope) => Some((scope, true)),
+            _ => None,
+        },
+        Op::Atom(atom) => match atom.mem_order {
+            MemOrder::Strong(scope) => Some((scope, true)),
+            _ => None,
+        },
+        Op::SuLd(ld) => match ld.mem_order {
+            MemOrder::Strong(scope) => Some((scope, false)),
+            _ => None,
+        },
+        Op::SuSt(st) => match st.mem_order {
+            MemOrder::Strong(scope) => Some((scope, true)),
+            _ => None,
+        },
+        _ => None,
+    };
+    let (scope, fence_after) = scope?;
+
+    if fence_after {
+        // Push the original access first and replace `op` with the trailing fence, since
+        // `LegalizeBuilder` only has a "push before" primitive. `legalize_op` won't visit this
+        // pushed copy again, so legalize it here instead of leaving that to the normal pass.
+        let mut access = op.clone();
+        as_sm50_op_mut(&mut access).legalize(b);
+        b.push_op(access);
+        Some(Op::MemBar(OpMemBar { scope }))
+    } else {
+        b.push_op(OpMemBar { scope });
+        None
+    }
+}
+
+/// Asserts that `src`, if it's a GPR, names the low half of a properly
+/// aligned register pair (even base index, paired with its consecutive high
+/// half). F64 ops read their 64-bit operands this way, so the hardware
+/// silently reads garbage out of the wrong registers if the allocator ever
+/// hands back an odd base index here; borrowed from the `debug_assert_valid_regpair!`
+/// idea in the s390x emitter to turn that into a caught invariant instead.
+fn debug_assert_valid_regpair(src: Src) {
+    if let SrcRef::Reg(reg) = src.src_ref {
+        if reg.file() == RegFile::GPR {
+            debug_assert_eq!(reg.comps(), 2, "F64 src must be a register pair");
+            debug_assert_eq!(reg.base_idx() % 2, 0, "F64 src pair must be even-aligned");
+        }
+    }
+}
+
+/// [`debug_assert_valid_regpair`] for a `Dst`.
+fn debug_assert_valid_regpair_dst(dst: Dst) {
+    if let Dst::Reg(reg) = dst {
+        if reg.file() == RegFile::GPR {
+            debug_assert_eq!(reg.comps(), 2, "F64 dst must be a register pair");
+            debug_assert_eq!(reg.base_idx() % 2, 0, "F64 dst pair must be even-aligned");
+        }
+    }
+}
+
 //
 // Implementations of SM50Op for each op we support on Maxwell/Pascal
 //
@@ -384,22 +1367,7 @@ impl SM50Op for OpFAdd {
             e.set_src_imm32(20..52, imm32);
             e.set_bit(55, self.ftz);
         } else {
-            match &self.srcs[1].src_ref {
-                SrcRef::Zero | SrcRef::Reg(_) => {
-                    e.set_opcode(0x5c58);
-                    e.set_reg_fmod_src(20..28, 49, 45, self.srcs[1]);
-                }
-                SrcRef::Imm32(imm) => {
-                    e.set_opcode(0x3858);
-                    e.set_src_imm_f20(20..39, 56, *imm);
-                    assert!(self.srcs[1].src_mod.is_none());
-                }
-                SrcRef::CBuf(_) => {
-                    e.set_opcode(0x4c58);
-                    e.set_cb_fmod_src(20..39, 49, 45, self.srcs[1]);
-                }
-                _ => panic!("Unsupported src type"),
-            }
+            FADD_SRC1_FORMS.encode(e, self.srcs[1]);
 
             e.set_dst(self.dst);
             e.set_reg_fmod_src(8..16, 46, 48, self.srcs[0]);
@@ -473,21 +1441,7 @@ impl SM50Op for OpFMnMx {
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        match &self.srcs[1].src_ref {
-            SrcRef::Imm32(imm32) => {
-                e.set_opcode(0x3860);
-                e.set_src_imm_f20(20..39, 56, *imm32);
-            }
-            SrcRef::Zero | SrcRef::Reg(_) => {
-                e.set_opcode(0x5c60);
-                e.set_reg_fmod_src(20..28, 49, 45, self.srcs[1]);
-            }
-            SrcRef::CBuf(_) => {
-                e.set_opcode(0x4c60);
-                e.set_cb_fmod_src(20..39, 49, 45, self.srcs[1]);
-            }
-            src => panic!("Unsupported src type for FMNMX: {src}"),
-        }
+        FMNMX_SRC1_FORMS.encode(e, self.srcs[1]);
 
         e.set_reg_fmod_src(8..16, 46, 48, self.srcs[0]);
         e.set_dst(self.dst);
@@ -519,21 +1473,7 @@ impl SM50Op for OpFMul {
                     ^ self.srcs[1].src_mod.has_fneg(),
             );
         } else {
-            match &self.srcs[1].src_ref {
-                SrcRef::Imm32(imm32) => {
-                    e.set_opcode(0x3868);
-                    e.set_src_imm_f20(20..39, 56, *imm32);
-                }
-                SrcRef::Zero | SrcRef::Reg(_) => {
-                    e.set_opcode(0x5c68);
-                    e.set_reg_src(20..28, self.srcs[1]);
-                }
-                SrcRef::CBuf(cbuf) => {
-                    e.set_opcode(0x4c68);
-                    e.set_src_cb(20..39, cbuf);
-                }
-                src => panic!("Unsupported src type for FMUL: {src}"),
-            }
+            FMUL_SRC1_FORMS.encode(e, self.srcs[1]);
 
             e.set_rnd_mode(39..41, self.rnd_mode);
             e.set_field(41..44, 0x0_u8); // TODO: PDIV
@@ -559,21 +1499,7 @@ impl SM50Op for OpRro {
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        match &self.src.src_ref {
-            SrcRef::Imm32(imm32) => {
-                e.set_opcode(0x3890);
-                e.set_src_imm_f20(20..39, 56, *imm32);
-            }
-            SrcRef::Zero | SrcRef::Reg(_) => {
-                e.set_opcode(0x5c90);
-                e.set_reg_fmod_src(20..28, 49, 45, self.src);
-            }
-            SrcRef::CBuf(_) => {
-                e.set_opcode(0x4c90);
-                e.set_cb_fmod_src(20..39, 49, 45, self.src);
-            }
-            src => panic!("Unsupported src type for RRO: {src}"),
-        }
+        RRO_SRC_FORMS.encode(e, self.src);
 
         e.set_dst(self.dst);
         e.set_field(
@@ -586,6 +1512,142 @@ impl SM50Op for OpRro {
     }
 }
 
+impl OpMuFu {
+    /// `MUFU.TANH` doesn't exist on any SM50 variant and `MUFU.SQRT` only
+    /// appears on SM52+, so both have to be built out of the transcendentals
+    /// SM50 does have (`EXP2`, `LOG2`, `RCP`, `RSQ`). Returns `None` when
+    /// `self.op` already has direct hardware support and needs no lowering.
+    fn lower_sm50(&self, sm: &ShaderModel50, b: &mut LegalizeBuilder) -> Option<Op> {
+        match self.op {
+            MuFuOp::Tanh => Some(self.lower_tanh(b)),
+            MuFuOp::Sqrt if sm.sm < 52 => Some(self.lower_sqrt(b)),
+            _ => None,
+        }
+    }
+
+    fn lower_tanh(&self, b: &mut LegalizeBuilder) -> Op {
+        // tanh(x) = 1 - 2/(e^(2x) + 1), and e^(2x) = exp2(2*log2(e)*x) since SM50
+        // only has MUFU.EXP2, not a base-e exponential. Clamp the input to +-9
+        // first: that keeps exp2(2*log2(e)*x) at or below e^18 (about 6.6e7),
+        // far from f32 overflow, and tanh(9) is within a few ULPs of +-1.0
+        // anyway, so the clamp doubles as
+        // the saturated branch without needing a separate select.
+        let clamped_hi = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpFMnMx {
+            dst: clamped_hi.into(),
+            srcs: [self.src, 9.0_f32.into()],
+            min: true.into(),
+            ftz: false,
+        });
+        let clamped = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpFMnMx {
+            dst: clamped.into(),
+            srcs: [clamped_hi.into(), (-9.0_f32).into()],
+            min: false.into(),
+            ftz: false,
+        });
+
+        let two_x = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpFMul {
+            dst: two_x.into(),
+            srcs: [clamped.into(), (2.0 * std::f32::consts::LOG2_E).into()],
+            rnd_mode: FRndMode::NearestEven,
+            ftz: false,
+            dnz: false,
+            saturate: false,
+        });
+
+        let exp2 = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpMuFu {
+            dst: exp2.into(),
+            src: two_x.into(),
+            op: MuFuOp::Exp2,
+        });
+
+        let denom = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpFAdd {
+            dst: denom.into(),
+            srcs: [exp2.into(), 1.0_f32.into()],
+            rnd_mode: FRndMode::NearestEven,
+            ftz: false,
+            saturate: false,
+        });
+
+        let rcp = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpMuFu {
+            dst: rcp.into(),
+            src: denom.into(),
+            op: MuFuOp::Rcp,
+        });
+
+        Op::FFma(OpFFma {
+            dst: self.dst,
+            srcs: [rcp.into(), (-2.0_f32).into(), 1.0_f32.into()],
+            rnd_mode: FRndMode::NearestEven,
+            saturate: false,
+            ftz: false,
+            dnz: false,
+        })
+    }
+
+    fn lower_sqrt(&self, b: &mut LegalizeBuilder) -> Op {
+        // sqrt(x) = x * rsqrt(x) everywhere except the two inputs rsqrt can't
+        // carry through correctly: rsqrt(0.0) is +inf, so x*rsqrt(x) becomes
+        // 0*inf = NaN instead of the correct 0.0, and rsqrt(+inf) is 0.0, giving
+        // inf*0 = NaN instead of +inf. Select the original input back out for
+        // both edge cases rather than letting the multiply produce NaN.
+        let rsq = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpMuFu {
+            dst: rsq.into(),
+            src: self.src,
+            op: MuFuOp::Rsq,
+        });
+
+        let product = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpFMul {
+            dst: product.into(),
+            srcs: [self.src, rsq.into()],
+            rnd_mode: FRndMode::NearestEven,
+            ftz: false,
+            dnz: false,
+            saturate: false,
+        });
+
+        let is_zero = b.alloc_ssa(RegFile::Pred, 1);
+        b.push_op(OpFSetP {
+            dst: is_zero.into(),
+            srcs: [self.src, 0.0_f32.into()],
+            cmp_op: FloatCmpOp::OrdEq,
+            set_op: PredSetOp::And,
+            accum: true.into(),
+            ftz: false,
+        });
+
+        let is_inf = b.alloc_ssa(RegFile::Pred, 1);
+        b.push_op(OpFSetP {
+            dst: is_inf.into(),
+            srcs: [self.src, f32::INFINITY.into()],
+            cmp_op: FloatCmpOp::OrdEq,
+            set_op: PredSetOp::And,
+            accum: true.into(),
+            ftz: false,
+        });
+
+        let selected = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpSel {
+            dst: selected.into(),
+            srcs: [self.src, product.into()],
+            cond: is_zero.into(),
+        });
+
+        Op::Sel(OpSel {
+            dst: self.dst,
+            srcs: [self.src, selected.into()],
+            cond: is_inf.into(),
+        })
+    }
+}
+
 impl SM50Op for OpMuFu {
     fn legalize(&mut self, b: &mut LegalizeBuilder) {
         b.copy_alu_src_if_not_reg(&mut self.src, RegFile::GPR, SrcType::GPR);
@@ -613,8 +1675,11 @@ impl SM50Op for OpMuFu {
                 MuFuOp::Rsq64H => 7_u8,
                 // SQRT is only on SM52 and later
                 MuFuOp::Sqrt if e.sm.sm >= 52 => 8_u8,
-                MuFuOp::Sqrt => panic!("MUFU.SQRT not supported on SM50"),
-                MuFuOp::Tanh => panic!("MUFU.TANH not supported on SM50"),
+                // `ShaderModel50::supports` rejects both of these before `encode` is ever
+                // reached, so there's no bit pattern to pick here.
+                MuFuOp::Sqrt | MuFuOp::Tanh => {
+                    unreachable!("supports() rejects this before encode is called")
+                }
             },
         );
     }
@@ -684,21 +1749,7 @@ impl SM50Op for OpFSet {
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        match &self.srcs[1].src_ref {
-            SrcRef::Imm32(imm32) => {
-                e.set_opcode(0x3000);
-                e.set_src_imm_f20(20..39, 56, *imm32);
-            }
-            SrcRef::Zero | SrcRef::Reg(_) => {
-                e.set_opcode(0x5800);
-                e.set_reg_fmod_src(20..28, 44, 53, self.srcs[1]);
-            }
-            SrcRef::CBuf(_) => {
-                e.set_opcode(0x4800);
-                e.set_cb_fmod_src(20..39, 44, 6, self.srcs[1]);
-            }
-            src => panic!("Unsupported src type for FSET: {src}"),
-        }
+        FSET_SRC1_FORMS.encode(e, self.srcs[1]);
 
         e.set_reg_fmod_src(8..16, 54, 43, self.srcs[0]);
         e.set_pred_src(39..42, 42, SrcRef::True.into());
@@ -721,21 +1772,7 @@ impl SM50Op for OpFSetP {
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        match &self.srcs[1].src_ref {
-            SrcRef::Imm32(imm32) => {
-                e.set_opcode(0x36b0);
-                e.set_src_imm_f20(20..39, 56, *imm32);
-            }
-            SrcRef::Zero | SrcRef::Reg(_) => {
-                e.set_opcode(0x5bb0);
-                e.set_reg_fmod_src(20..28, 44, 6, self.srcs[1]);
-            }
-            SrcRef::CBuf(_) => {
-                e.set_opcode(0x4bb0);
-                e.set_cb_fmod_src(20..39, 44, 6, self.srcs[1]);
-            }
-            src => panic!("Unsupported src type for FSETP: {src}"),
-        }
+        FSETP_SRC1_FORMS.encode(e, self.srcs[1]);
 
         e.set_pred_dst(3..6, self.dst);
         e.set_pred_dst(0..3, Dst::None); // dst1
@@ -796,25 +1833,16 @@ impl SM50Op for OpDAdd {
         swap_srcs_if_not_reg(src0, src1, GPR);
         b.copy_alu_src_if_not_reg(src0, GPR, SrcType::F64);
         b.copy_alu_src_if_f20_overflow(src1, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src0, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src1, GPR, SrcType::F64);
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        match &self.srcs[1].src_ref {
-            SrcRef::Zero | SrcRef::Reg(_) => {
-                e.set_opcode(0x5c70);
-                e.set_reg_fmod_src(20..28, 49, 45, self.srcs[1]);
-            }
-            SrcRef::Imm32(imm) => {
-                e.set_opcode(0x3870);
-                e.set_src_imm_f20(20..39, 56, *imm);
-                assert!(self.srcs[1].src_mod.is_none());
-            }
-            SrcRef::CBuf(_) => {
-                e.set_opcode(0x4c70);
-                e.set_cb_fmod_src(20..39, 49, 45, self.srcs[1]);
-            }
-            _ => panic!("Unsupported src type"),
-        }
+        debug_assert_valid_regpair(self.srcs[0]);
+        debug_assert_valid_regpair(self.srcs[1]);
+        debug_assert_valid_regpair_dst(self.dst);
+
+        DADD_SRC1_FORMS.encode(e, self.srcs[1]);
 
         e.set_dst(self.dst);
         e.set_reg_fmod_src(8..16, 46, 48, self.srcs[0]);
@@ -837,9 +1865,17 @@ impl SM50Op for OpDFma {
         } else {
             b.copy_alu_src_if_not_reg(src2, GPR, SrcType::F64);
         }
+        b.copy_alu_src_if_misaligned_pair(src0, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src1, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src2, GPR, SrcType::F64);
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
+        debug_assert_valid_regpair(self.srcs[0]);
+        debug_assert_valid_regpair(self.srcs[1]);
+        debug_assert_valid_regpair(self.srcs[2]);
+        debug_assert_valid_regpair_dst(self.dst);
+
         match &self.srcs[2].src_ref {
             SrcRef::Zero | SrcRef::Reg(_) => {
                 match &self.srcs[1].src_ref {
@@ -891,9 +1927,15 @@ impl SM50Op for OpDMnMx {
         swap_srcs_if_not_reg(src0, src1, GPR);
         b.copy_alu_src_if_not_reg(src0, GPR, SrcType::F64);
         b.copy_alu_src_if_f20_overflow(src1, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src0, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src1, GPR, SrcType::F64);
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
+        debug_assert_valid_regpair(self.srcs[0]);
+        debug_assert_valid_regpair(self.srcs[1]);
+        debug_assert_valid_regpair_dst(self.dst);
+
         match &self.srcs[1].src_ref {
             SrcRef::Zero | SrcRef::Reg(_) => {
                 e.set_opcode(0x5c50);
@@ -925,9 +1967,15 @@ impl SM50Op for OpDMul {
         swap_srcs_if_not_reg(src0, src1, GPR);
         b.copy_alu_src_if_not_reg(src0, GPR, SrcType::F64);
         b.copy_alu_src_if_f20_overflow(src1, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src0, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src1, GPR, SrcType::F64);
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
+        debug_assert_valid_regpair(self.srcs[0]);
+        debug_assert_valid_regpair(self.srcs[1]);
+        debug_assert_valid_regpair_dst(self.dst);
+
         match &self.srcs[1].src_ref {
             SrcRef::Zero | SrcRef::Reg(_) => {
                 e.set_opcode(0x5c80);
@@ -968,9 +2016,14 @@ impl SM50Op for OpDSetP {
         }
         b.copy_alu_src_if_not_reg(src0, GPR, SrcType::F64);
         b.copy_alu_src_if_f20_overflow(src1, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src0, GPR, SrcType::F64);
+        b.copy_alu_src_if_misaligned_pair(src1, GPR, SrcType::F64);
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
+        debug_assert_valid_regpair(self.srcs[0]);
+        debug_assert_valid_regpair(self.srcs[1]);
+
         match &self.srcs[1].src_ref {
             SrcRef::Zero | SrcRef::Reg(_) => {
                 e.set_opcode(0x5b80);
@@ -997,6 +2050,646 @@ impl SM50Op for OpDSetP {
     }
 }
 
+//
+// Software fp64 emulation for `ShaderModel50::emulate_fp64` parts. Doubles
+// are unpacked into a sign bit, biased exponent, and 53-bit significand
+// (implicit leading one folded into the high word) and recombined using
+// `OpIAdd2`/`OpShf`/`OpLop2`/`OpFlo`/`OpISetP`, the same integer ops
+// compiler-builtins' soft-float routines are built from. This only covers
+// the common, already-normalized case and round-to-nearest: an op with one
+// of the other three rounding modes keeps the (slow) hardware op, while
+// NaNs, infinities and subnormals are not detected at run time and go
+// through the same integer sequence without special handling.
+//
+
+/// Intercepts the double-precision ops when running on an `emulate_fp64`
+/// part, returning their software replacement. Returns `None` to leave the
+/// hardware op in place, either because the op isn't one of these or
+/// because it's outside what the emulation covers.
+fn lower_f64_op(op: &mut Op, b: &mut LegalizeBuilder) -> Option<Op> {
+    match op {
+        Op::DAdd(add) if add.rnd_mode == FRndMode::NearestEven => {
+            Some(lower_f64_add(b, add.dst, add.srcs[0], add.srcs[1]))
+        }
+        Op::DMul(mul) if mul.rnd_mode == FRndMode::NearestEven => {
+            Some(lower_f64_mul(b, mul.dst, mul.srcs[0], mul.srcs[1]))
+        }
+        Op::DFma(fma) if fma.rnd_mode == FRndMode::NearestEven => Some(lower_f64_fma(
+            b, fma.dst, fma.srcs[0], fma.srcs[1], fma.srcs[2],
+        )),
+        Op::DSetP(setp) => lower_f64_setp(b, setp),
+        _ => None,
+    }
+}
+
+/// Splits an F64 `Src` (still SSA at legalize time) into its sign bit (0 or
+/// 1), biased 11-bit exponent, and 53-bit significand with the implicit
+/// leading one folded into the high word, as four 32-bit values.
+fn unpack_f64(b: &mut LegalizeBuilder, src: Src) -> [Src; 4] {
+    let words = src
+        .as_ssa()
+        .expect("F64 operand must be SSA for fp64 emulation");
+    let lo: Src = words[0].into();
+    let hi: Src = words[1].into();
+
+    let sign = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: sign.into(),
+        low: hi,
+        high: SrcRef::Zero.into(),
+        shift: Src::from(31u32),
+        dst_high: false,
+        wrap: false,
+        right: true,
+        data_type: IntType::U32,
+    });
+
+    let exp_shifted = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: exp_shifted.into(),
+        low: hi,
+        high: SrcRef::Zero.into(),
+        shift: Src::from(20u32),
+        dst_high: false,
+        wrap: false,
+        right: true,
+        data_type: IntType::U32,
+    });
+    let exp = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: exp.into(),
+        srcs: [exp_shifted.into(), Src::from(0x7ff_u32)],
+        op: LogicOp2::And,
+    });
+
+    let mant_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: mant_hi.into(),
+        srcs: [hi, Src::from(0x000f_ffff_u32)],
+        op: LogicOp2::And,
+    });
+    let mant_hi_with_one = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: mant_hi_with_one.into(),
+        srcs: [mant_hi.into(), Src::from(0x0010_0000_u32)],
+        op: LogicOp2::Or,
+    });
+
+    // IEEE-754 doubles have no implicit leading one when `exp == 0` (zero
+    // and subnormals); folding it in unconditionally would corrupt every
+    // zero/subnormal operand by effectively adding 2^52 to its mantissa.
+    let exp_is_zero = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: exp_is_zero.into(),
+        set_op: PredSetOp::And,
+        cmp_op: IntCmpOp::Eq,
+        cmp_type: IntCmpType::U32,
+        srcs: [exp.into(), Src::from(0u32)],
+        accum: true.into(),
+        ex: false,
+    });
+    let mant_hi_implicit = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: mant_hi_implicit.into(),
+        srcs: [mant_hi.into(), mant_hi_with_one.into()],
+        cond: exp_is_zero.into(),
+    });
+
+    [sign.into(), exp.into(), mant_hi_implicit.into(), lo]
+}
+
+/// Inverse of `unpack_f64`: assembles the hi/lo words from a sign bit,
+/// biased exponent, and 53-bit significand (implicit leading one still set
+/// in `mant_hi`).
+fn pack_f64(
+    b: &mut LegalizeBuilder,
+    sign: Src,
+    exp: Src,
+    mant_hi: Src,
+    mant_lo: Src,
+) -> (Src, Src) {
+    let mant_hi_masked = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: mant_hi_masked.into(),
+        srcs: [mant_hi, Src::from(0x000f_ffff_u32)],
+        op: LogicOp2::And,
+    });
+
+    let exp_shifted = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShl {
+        dst: exp_shifted.into(),
+        src: exp,
+        shift: Src::from(20u32),
+        wrap: false,
+    });
+
+    let sign_shifted = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShl {
+        dst: sign_shifted.into(),
+        src: sign,
+        shift: Src::from(31u32),
+        wrap: false,
+    });
+
+    let hi_no_sign = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: hi_no_sign.into(),
+        srcs: [exp_shifted.into(), mant_hi_masked.into()],
+        op: LogicOp2::Or,
+    });
+    let hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: hi.into(),
+        srcs: [hi_no_sign.into(), sign_shifted.into()],
+        op: LogicOp2::Or,
+    });
+
+    (hi.into(), mant_lo)
+}
+
+/// Renormalizes a significand whose leading one may have drifted off bit 20
+/// of `mant_hi` (a carry-out from addition, or cancellation during a
+/// subtraction) back to the canonical form, adjusting `exp` to match and
+/// rounding to nearest by folding in the one bit of precision the
+/// renormalizing shift drops.
+fn normalize_and_round_f64(
+    b: &mut LegalizeBuilder,
+    exp: Src,
+    mant_hi: Src,
+    mant_lo: Src,
+) -> (Src, Src, Src) {
+    let lz = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpFlo {
+        dst: lz.into(),
+        src: mant_hi,
+        signed: false,
+        return_shift_amount: true,
+    });
+
+    // `lz` is the shift that would put the leading one at bit 31; we want it
+    // at bit 20 instead, 11 bits lower.
+    let norm_shift = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: norm_shift.into(),
+        srcs: [lz.into(), Src::from(11u32).ineg()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+    let round_shift = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: round_shift.into(),
+        srcs: [norm_shift.into(), Src::from(1u32).ineg()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+
+    let norm_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: norm_hi.into(),
+        low: mant_lo,
+        high: mant_hi,
+        shift: norm_shift.into(),
+        dst_high: true,
+        wrap: true,
+        right: true,
+        data_type: IntType::U64,
+    });
+    let norm_lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: norm_lo.into(),
+        low: mant_lo,
+        high: mant_hi,
+        shift: norm_shift.into(),
+        dst_high: false,
+        wrap: true,
+        right: true,
+        data_type: IntType::U64,
+    });
+    let round_bit = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: round_bit.into(),
+        low: mant_lo,
+        high: mant_hi,
+        shift: round_shift.into(),
+        dst_high: false,
+        wrap: true,
+        right: true,
+        data_type: IntType::U64,
+    });
+    let round_bit_masked = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: round_bit_masked.into(),
+        srcs: [round_bit.into(), Src::from(1u32)],
+        op: LogicOp2::And,
+    });
+    let round_bit = round_bit_masked;
+
+    let new_exp = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: new_exp.into(),
+        srcs: [exp, norm_shift.into()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+
+    let round_carry = b.alloc_ssa(RegFile::Carry, 1);
+    let rounded_lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: rounded_lo.into(),
+        srcs: [norm_lo.into(), round_bit.into()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: round_carry.into(),
+    });
+    let rounded_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: rounded_hi.into(),
+        srcs: [norm_hi.into(), Src::from(0u32)],
+        carry_in: round_carry.into(),
+        carry_out: Dst::None,
+    });
+
+    (new_exp.into(), rounded_hi.into(), rounded_lo.into())
+}
+
+/// Two's-complement-negates a 64-bit `(hi, lo)` pair.
+fn negate64(b: &mut LegalizeBuilder, hi: Src, lo: Src) -> (Src, Src) {
+    let lo_dst = b.alloc_ssa(RegFile::GPR, 1);
+    let hi_dst = b.alloc_ssa(RegFile::GPR, 1);
+    let hi_op = lower_i64_iadd2(
+        b,
+        [lo_dst.into(), hi_dst.into()],
+        [lo.bnot(), hi.bnot()],
+        [Src::from(1u32), Src::from(0u32)],
+    );
+    b.push_op(hi_op);
+    (hi_dst.into(), lo_dst.into())
+}
+
+fn lower_f64_add(b: &mut LegalizeBuilder, dst: Dst, src0: Src, src1: Src) -> Op {
+    let [sign0, exp0, mant0_hi, mant0_lo] = unpack_f64(b, src0);
+    let [sign1, exp1, mant1_hi, mant1_lo] = unpack_f64(b, src1);
+
+    let e0_ge_e1 = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: e0_ge_e1.into(),
+        set_op: PredSetOp::And,
+        cmp_op: IntCmpOp::Ge,
+        cmp_type: IntCmpType::U32,
+        srcs: [exp0, exp1],
+        accum: true.into(),
+        ex: false,
+    });
+    let e0_ge_e1: Src = e0_ge_e1.into();
+
+    let sel = |b: &mut LegalizeBuilder, a: Src, c: Src| -> Src {
+        let dst = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpSel {
+            dst: dst.into(),
+            srcs: [a, c],
+            cond: e0_ge_e1,
+        });
+        dst.into()
+    };
+
+    let big_exp = sel(b, exp0, exp1);
+    let small_exp = sel(b, exp1, exp0);
+    let big_sign = sel(b, sign0, sign1);
+    let small_sign = sel(b, sign1, sign0);
+    let big_hi = sel(b, mant0_hi, mant1_hi);
+    let big_lo = sel(b, mant0_lo, mant1_lo);
+    let small_hi = sel(b, mant1_hi, mant0_hi);
+    let small_lo = sel(b, mant1_lo, mant0_lo);
+
+    let shift = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: shift.into(),
+        srcs: [big_exp, small_exp.ineg()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+    let shift: Src = shift.into();
+
+    // OpShf's 64-bit funnel shift wraps the shift amount modulo 64 rather
+    // than saturating, but `shift` is an 11-bit exponent difference that can
+    // be 64 or more (e.g. `1.0 + 1e-30`): the small operand's mantissa has
+    // been shifted fully out and contributes nothing, not whatever bits the
+    // wrapped-around shift amount would read back. Clamp the amount fed to
+    // the funnel shift and force the aligned mantissa to zero separately.
+    let shift_ge_64 = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: shift_ge_64.into(),
+        set_op: PredSetOp::And,
+        cmp_op: IntCmpOp::Ge,
+        cmp_type: IntCmpType::U32,
+        srcs: [shift, Src::from(64u32)],
+        accum: true.into(),
+        ex: false,
+    });
+    let shift_ge_64: Src = shift_ge_64.into();
+    let shift_clamped = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIMnMx {
+        dst: shift_clamped.into(),
+        srcs: [shift, Src::from(63u32)],
+        cmp_type: IntCmpType::U32,
+        min: true.into(),
+    });
+    let shift_clamped: Src = shift_clamped.into();
+
+    let aligned_hi_shifted = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: aligned_hi_shifted.into(),
+        low: small_lo,
+        high: small_hi,
+        shift: shift_clamped,
+        dst_high: true,
+        wrap: true,
+        right: true,
+        data_type: IntType::U64,
+    });
+    let aligned_lo_shifted = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpShf {
+        dst: aligned_lo_shifted.into(),
+        low: small_lo,
+        high: small_hi,
+        shift: shift_clamped,
+        dst_high: false,
+        wrap: true,
+        right: true,
+        data_type: IntType::U64,
+    });
+    let aligned_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: aligned_hi.into(),
+        srcs: [Src::from(0u32), aligned_hi_shifted.into()],
+        cond: shift_ge_64,
+    });
+    let aligned_lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: aligned_lo.into(),
+        srcs: [Src::from(0u32), aligned_lo_shifted.into()],
+        cond: shift_ge_64,
+    });
+
+    let signs_differ = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: signs_differ.into(),
+        set_op: PredSetOp::And,
+        cmp_op: IntCmpOp::Ne,
+        cmp_type: IntCmpType::U32,
+        srcs: [big_sign, small_sign],
+        accum: true.into(),
+        ex: false,
+    });
+
+    let (neg_hi, neg_lo) = negate64(b, aligned_hi.into(), aligned_lo.into());
+    let addend_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: addend_hi.into(),
+        srcs: [neg_hi, aligned_hi.into()],
+        cond: signs_differ.into(),
+    });
+    let addend_lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: addend_lo.into(),
+        srcs: [neg_lo, aligned_lo.into()],
+        cond: signs_differ.into(),
+    });
+
+    let sum_dst = [
+        b.alloc_ssa(RegFile::GPR, 1).into(),
+        b.alloc_ssa(RegFile::GPR, 1).into(),
+    ];
+    let sum_hi_op = lower_i64_iadd2(
+        b,
+        sum_dst,
+        [big_lo, big_hi],
+        [addend_lo.into(), addend_hi.into()],
+    );
+    let Op::IAdd2(sum_hi_add) = &sum_hi_op else {
+        unreachable!()
+    };
+    let sum_hi: Src = sum_hi_add.dst.into();
+    b.push_op(sum_hi_op);
+    let sum_lo: Src = sum_dst[0];
+
+    // A negative sum means the smaller-magnitude, opposite-signed addend
+    // actually won the comparison; flip back to a magnitude and the result
+    // takes its sign instead of the bigger operand's.
+    let sum_negative = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: sum_negative.into(),
+        set_op: PredSetOp::And,
+        cmp_op: IntCmpOp::Lt,
+        cmp_type: IntCmpType::I32,
+        srcs: [sum_hi, Src::from(0u32)],
+        accum: true.into(),
+        ex: false,
+    });
+    let (sum_neg_hi, sum_neg_lo) = negate64(b, sum_hi, sum_lo);
+    let result_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: result_hi.into(),
+        srcs: [sum_neg_hi, sum_hi],
+        cond: sum_negative.into(),
+    });
+    let result_lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpSel {
+        dst: result_lo.into(),
+        srcs: [sum_neg_lo, sum_lo],
+        cond: sum_negative.into(),
+    });
+
+    let result_sign = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: result_sign.into(),
+        srcs: [big_sign, sum_negative.into()],
+        op: LogicOp2::Xor,
+    });
+
+    let (exp, hi, lo) =
+        normalize_and_round_f64(b, big_exp, result_hi.into(), result_lo.into());
+    let (hi, lo) = pack_f64(b, result_sign.into(), exp, hi, lo);
+
+    let dst_ssa = dst.as_ssa().expect("F64 dst must be SSA for fp64 emulation");
+    b.push_op(OpLop2 {
+        dst: dst_ssa[0].into(),
+        srcs: [lo, Src::from(0u32)],
+        op: LogicOp2::Or,
+    });
+    Op::Lop2(OpLop2 {
+        dst: dst_ssa[1].into(),
+        srcs: [hi, Src::from(0u32)],
+        op: LogicOp2::Or,
+    })
+}
+
+/// 53x53-bit significand multiply, keeping only the three partial products
+/// that land in the high 64 bits of the 106-bit product (the low limb's
+/// contribution to `mant0_lo * mant1_lo` falls below the bits this
+/// emulation keeps) and returning the resulting unnormalized (hi, lo) pair.
+fn mul_significands(
+    b: &mut LegalizeBuilder,
+    mant0_hi: Src,
+    mant0_lo: Src,
+    mant1_hi: Src,
+    mant1_lo: Src,
+) -> (Src, Src) {
+    let hi_hi = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIMad {
+        dst: hi_hi.into(),
+        srcs: [mant0_hi, mant1_hi, Src::from(0u32)],
+        signed: false,
+    });
+    let cross = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIMad {
+        dst: cross.into(),
+        srcs: [mant0_hi, mant1_lo, hi_hi.into()],
+        signed: false,
+    });
+    let lo = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIMad {
+        dst: lo.into(),
+        srcs: [mant0_lo, mant1_hi, cross.into()],
+        signed: false,
+    });
+    (cross.into(), lo.into())
+}
+
+fn lower_f64_mul(b: &mut LegalizeBuilder, dst: Dst, src0: Src, src1: Src) -> Op {
+    let [sign0, exp0, mant0_hi, mant0_lo] = unpack_f64(b, src0);
+    let [sign1, exp1, mant1_hi, mant1_lo] = unpack_f64(b, src1);
+
+    let exp_sum = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: exp_sum.into(),
+        srcs: [exp0, exp1],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+    // Adding the two biased exponents double-counts the 1023 bias.
+    let exp = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpIAdd2 {
+        dst: exp.into(),
+        srcs: [exp_sum.into(), Src::from(1023u32).ineg()],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: Dst::None,
+    });
+    let exp: Src = exp.into();
+
+    let sign = b.alloc_ssa(RegFile::GPR, 1);
+    b.push_op(OpLop2 {
+        dst: sign.into(),
+        srcs: [sign0, sign1],
+        op: LogicOp2::Xor,
+    });
+
+    let (mant_hi, mant_lo) = mul_significands(b, mant0_hi, mant0_lo, mant1_hi, mant1_lo);
+    let (exp, hi, lo) = normalize_and_round_f64(b, exp, mant_hi, mant_lo);
+    let (hi, lo) = pack_f64(b, sign.into(), exp, hi, lo);
+
+    let dst_ssa = dst.as_ssa().expect("F64 dst must be SSA for fp64 emulation");
+    b.push_op(OpLop2 {
+        dst: dst_ssa[0].into(),
+        srcs: [lo, Src::from(0u32)],
+        op: LogicOp2::Or,
+    });
+    Op::Lop2(OpLop2 {
+        dst: dst_ssa[1].into(),
+        srcs: [hi, Src::from(0u32)],
+        op: LogicOp2::Or,
+    })
+}
+
+fn lower_f64_fma(
+    b: &mut LegalizeBuilder,
+    dst: Dst,
+    src0: Src,
+    src1: Src,
+    src2: Src,
+) -> Op {
+    let product_hi = b.alloc_ssa(RegFile::GPR, 2);
+    let mul = lower_f64_mul(b, product_hi.into(), src0, src1);
+    b.push_op(mul);
+    lower_f64_add(b, dst, product_hi.into(), src2)
+}
+
+/// Lowers a 64-bit float compare to a 64-bit unsigned compare of a
+/// sign-magnitude-to-monotonic "key" transform of each operand (flip all
+/// bits when negative, otherwise just force the sign bit to 1), which
+/// reproduces IEEE-754 total order for all finite values without having to
+/// unpack the exponent/mantissa at all.
+fn lower_f64_setp(b: &mut LegalizeBuilder, setp: &mut OpDSetP) -> Option<Op> {
+    let cmp_op = match setp.cmp_op {
+        FloatCmpOp::OrdLt => IntCmpOp::Lt,
+        FloatCmpOp::OrdEq => IntCmpOp::Eq,
+        FloatCmpOp::OrdLe => IntCmpOp::Le,
+        FloatCmpOp::OrdGt => IntCmpOp::Gt,
+        FloatCmpOp::OrdNe => IntCmpOp::Ne,
+        FloatCmpOp::OrdGe => IntCmpOp::Ge,
+        _ => return None,
+    };
+
+    let key = |b: &mut LegalizeBuilder, src: Src| -> [Src; 2] {
+        let words = src
+            .as_ssa()
+            .expect("F64 operand must be SSA for fp64 emulation");
+        let lo: Src = words[0].into();
+        let hi: Src = words[1].into();
+
+        let sign = b.alloc_ssa(RegFile::Pred, 1);
+        b.push_op(OpISetP {
+            dst: sign.into(),
+            set_op: PredSetOp::And,
+            cmp_op: IntCmpOp::Lt,
+            cmp_type: IntCmpType::I32,
+            srcs: [hi, Src::from(0u32)],
+            accum: true.into(),
+            ex: false,
+        });
+        let sign: Src = sign.into();
+
+        let mask_hi = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpSel {
+            dst: mask_hi.into(),
+            srcs: [Src::from(0xffff_ffff_u32), Src::from(0x8000_0000_u32)],
+            cond: sign,
+        });
+        let mask_lo = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpSel {
+            dst: mask_lo.into(),
+            srcs: [Src::from(0xffff_ffff_u32), Src::from(0u32)],
+            cond: sign,
+        });
+
+        let key_hi = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpLop2 {
+            dst: key_hi.into(),
+            srcs: [hi, mask_hi.into()],
+            op: LogicOp2::Xor,
+        });
+        let key_lo = b.alloc_ssa(RegFile::GPR, 1);
+        b.push_op(OpLop2 {
+            dst: key_lo.into(),
+            srcs: [lo, mask_lo.into()],
+            op: LogicOp2::Xor,
+        });
+
+        [key_lo.into(), key_hi.into()]
+    };
+
+    let key0 = key(b, setp.srcs[0]);
+    let key1 = key(b, setp.srcs[1]);
+
+    Some(lower_i64_isetp(
+        b,
+        setp.dst,
+        key0,
+        key1,
+        cmp_op,
+        false,
+    ))
+}
+
 impl SM50Op for OpBfe {
     fn legalize(&mut self, b: &mut LegalizeBuilder) {
         use RegFile::GPR;
@@ -1302,7 +2995,10 @@ impl SM50Op for OpISetP {
         e.set_reg_src(8..16, self.srcs[0]);
         e.set_pred_src(39..42, 42, self.accum);
 
-        e.set_bit(43, false); // .X
+        // .X: fold the carry predicate from a preceding 32-bit half of a
+        // multi-word compare into `accum` instead of treating it as a plain
+        // accumulator. See `lower_i64_isetp` for the chain that sets this.
+        e.set_bit(43, self.ex);
         e.set_pred_set_op(45..47, self.set_op);
 
         e.set_field(
@@ -1316,6 +3012,71 @@ impl SM50Op for OpISetP {
     }
 }
 
+/// Builds the low half of a 64-bit integer add: a plain `IADD2` whose carry
+/// out feeds `RegFile::Carry` for the high half to consume. Pushes the low
+/// `IADD2` via `b` and returns the high one, which the caller installs as
+/// the replacement for the 64-bit op being legalized.
+pub(crate) fn lower_i64_iadd2(
+    b: &mut LegalizeBuilder,
+    dst: [Dst; 2],
+    src0: [Src; 2],
+    src1: [Src; 2],
+) -> Op {
+    let carry = b.alloc_ssa(RegFile::Carry, 1);
+    b.push_op(OpIAdd2 {
+        dst: dst[0],
+        srcs: [src0[0], src1[0]],
+        carry_in: SrcRef::Zero.into(),
+        carry_out: carry.into(),
+    });
+    Op::IAdd2(OpIAdd2 {
+        dst: dst[1],
+        srcs: [src0[1], src1[1]],
+        carry_in: carry.into(),
+        carry_out: Dst::None,
+    })
+}
+
+/// Builds the carry-chained pair of `ISETP`s SM50 uses for a 64-bit integer
+/// compare, the pattern compiler-builtins lowers multi-word compares to: the
+/// low half always compares `U32` (unsigned `lt`/`ge` don't care about sign
+/// until the high word breaks the tie), producing a predicate that the high
+/// half folds in via `set_op: And` and the `.X` bit. Only the high half's
+/// `cmp_type` reflects the overall comparison's signedness. Pushes the low
+/// `ISETP` via `b` and returns the high one.
+pub(crate) fn lower_i64_isetp(
+    b: &mut LegalizeBuilder,
+    dst: Dst,
+    src0: [Src; 2],
+    src1: [Src; 2],
+    cmp_op: IntCmpOp,
+    signed: bool,
+) -> Op {
+    let lo_pred = b.alloc_ssa(RegFile::Pred, 1);
+    b.push_op(OpISetP {
+        dst: lo_pred.into(),
+        set_op: PredSetOp::And,
+        cmp_op,
+        cmp_type: IntCmpType::U32,
+        srcs: [src0[0], src1[0]],
+        accum: true.into(),
+        ex: false,
+    });
+    Op::ISetP(OpISetP {
+        dst,
+        set_op: PredSetOp::And,
+        cmp_op,
+        cmp_type: if signed {
+            IntCmpType::I32
+        } else {
+            IntCmpType::U32
+        },
+        srcs: [src0[1], src1[1]],
+        accum: lo_pred.into(),
+        ex: true,
+    })
+}
+
 impl SM50Op for OpLop2 {
     fn legalize(&mut self, b: &mut LegalizeBuilder) {
         use RegFile::GPR;
@@ -2004,10 +3765,15 @@ impl SM50Op for OpTxq {
                 TexQuery::Dimension => 1_u8,
                 TexQuery::TextureType => 2_u8,
                 TexQuery::SamplerPos => 5_u8,
-                // TexQuery::Filter => 0x10_u8,
-                // TexQuery::Lod => 0x12_u8,
-                // TexQuery::Wrap => 0x14_u8,
-                // TexQuery::BorderColour => 0x16,
+                // textureQueryLod()'s two components (base and clamped LOD), and the texture
+                // introspection queries NAK doesn't yet generate NIR for, but which the hardware
+                // opcode already supports -- `self.mask` is set by the caller to the matching
+                // component count for each (2 for Lod; however many Filter/Wrap/BorderColour
+                // need) the same way it already is for Dimension/TextureType/SamplerPos above.
+                TexQuery::Filter => 0x10_u8,
+                TexQuery::Lod => 0x12_u8,
+                TexQuery::Wrap => 0x14_u8,
+                TexQuery::BorderColour => 0x16_u8,
             },
         );
         e.set_field(31..35, self.mask);
@@ -2086,15 +3852,7 @@ impl SM50Op for OpSuLd {
             MemOrder::Strong(s) => s,
         };
 
-        e.set_field(
-            24..26,
-            match scope {
-                MemScope::CTA => 0_u8,
-                /* SM => 1_u8, */
-                MemScope::GPU => 2_u8,
-                MemScope::System => 3_u8,
-            },
-        );
+        e.set_field(24..26, suld_scope(scope));
 
         e.set_dst(self.dst);
 
@@ -2124,6 +3882,10 @@ impl SM50Op for OpSuSt {
 }
 
 impl SM50Encoder<'_> {
+    /// Shared by `OpAtom` and `OpSuAtom`, whose atom-op sub-fields use the same encoding.
+    /// `CmpExch`'s comparand and swap value are just the two adjacent registers making up its
+    /// (already two-component) data operand, the same way a `U64`/`I64` atom type's value spans
+    /// two adjacent registers -- no special-casing needed here beyond the sub-op code itself.
     fn set_atom_op(&mut self, range: Range<usize>, atom_op: AtomOp) {
         assert!(range.len() == 4);
         self.set_field(
@@ -2138,7 +3900,7 @@ impl SM50Encoder<'_> {
                 AtomOp::Or => 6_u8,
                 AtomOp::Xor => 7_u8,
                 AtomOp::Exch => 8_u8,
-                AtomOp::CmpExch => panic!("CmpXchg not yet supported"),
+                AtomOp::CmpExch => 9_u8,
             },
         );
     }
@@ -2168,22 +3930,9 @@ impl SM50Op for OpSuAtom {
             _ => panic!("Unsupported atom type {}", self.atom_type),
         };
 
-        let atom_op: u8 = match self.atom_op {
-            AtomOp::Add => 0,
-            AtomOp::Min => 1,
-            AtomOp::Max => 2,
-            AtomOp::Inc => 3,
-            AtomOp::Dec => 4,
-            AtomOp::And => 5,
-            AtomOp::Or => 6,
-            AtomOp::Xor => 7,
-            AtomOp::Exch => 8,
-            AtomOp::CmpExch => 0,
-        };
-
         e.set_image_dim(33..36, self.image_dim);
         e.set_field(36..39, atom_type);
-        e.set_field(29..33, atom_op);
+        e.set_atom_op(29..33, self.atom_op);
 
         // The hardware requires that we set .D on atomics.  This is safe to do
         // in in the emit code because it only affects format conversion, not
@@ -2275,6 +4024,12 @@ impl SM50Op for OpSt {
 }
 
 impl SM50Op for OpAtom {
+    // NOTE: this can't call `lower_unsupported_atom` for a float type SM50 lacks hardware
+    // support for (global F64, shared F32/F64): that lowering replaces one block with two, and
+    // `LegalizeBuilder` -- like every other `SM50Op::legalize` in this file -- can only insert
+    // straight-line ops ahead of the current instruction, not split its block. Actually emulating
+    // those atom types needs a pass with CFG-mutation access (a `&mut Function`, a
+    // `LabelAllocator`), run before this per-instruction walk, not from inside it.
     fn legalize(&mut self, b: &mut LegalizeBuilder) {
         legalize_ext_instr(self, b);
     }
@@ -2305,13 +4060,20 @@ impl SM50Op for OpAtom {
                         AtomType::F32 => 3_u8,
                         // NOTE: U128 => 4_u8,
                         AtomType::I64 => 5_u8,
-                        // TODO: do something about ATOMG.F64
-                        other => panic!("ATOMG.{other} not supported on SM50"),
+                        // `ShaderModel50::supports` rejects every other `AtomType` (e.g. F64)
+                        // before `encode` is ever reached.
+                        _ => unreachable!(
+                            "supports() rejects this before encode is called"
+                        ),
                     },
                 );
                 e.set_atom_op(52..56, self.atom_op);
             }
-            MemSpace::Local => panic!("Atomics do not support local"),
+            // `ShaderModel50::supports` rejects `MemSpace::Local` atomics before `encode` is
+            // ever reached.
+            MemSpace::Local => {
+                unreachable!("supports() rejects this before encode is called")
+            }
             MemSpace::Shared => {
                 e.set_opcode(0xec00);
                 e.set_mem_order(&self.mem_order);
@@ -2326,8 +4088,11 @@ impl SM50Op for OpAtom {
                         AtomType::I32 => 1_u8,
                         AtomType::U64 => 2_u8,
                         AtomType::I64 => 3_u8,
-                        // TODO: do something about ATOMS.F{32,64}
-                        other => panic!("ATOMS.{other} not supported on SM50"),
+                        // `ShaderModel50::supports` rejects every other `AtomType` (e.g.
+                        // F32/F64) before `encode` is ever reached.
+                        _ => unreachable!(
+                            "supports() rejects this before encode is called"
+                        ),
                     },
                 );
                 assert_eq!(self.addr_offset % 4, 0);
@@ -2338,6 +4103,174 @@ impl SM50Op for OpAtom {
     }
 }
 
+/// The two basic blocks [`lower_unsupported_atom`] builds to replace an atomic `ShaderModel50`
+/// can't encode. Splicing them into the function in place of the block that held the original
+/// `OpAtom` -- redirecting that block's predecessors to `loop_body` and its successors to `post`
+/// -- is the caller's job: building the replacement blocks is the part that's specific to how an
+/// unsupported atomic gets emulated, while wiring them into the CFG is generic graph surgery this
+/// file has no business knowing how to do.
+struct AtomCasLoop {
+    /// Computes the new value and retries `AtomOp::CmpExch` until it sees the value it expects;
+    /// branches back to itself (its own label) on a failed attempt.
+    loop_body: BasicBlock,
+    /// Resumes the original instruction stream with the atomic's destination holding the
+    /// pre-operation value, exactly as the unemulated `OpAtom` would have left it.
+    post: BasicBlock,
+}
+
+/// Expands an `OpAtom` whose `(mem_space, atom_type)` combination `ShaderModel50::supports`
+/// rejects (a global `F64` atomic, or a shared `F32`/`F64` one) into a compare-and-swap retry
+/// loop built entirely from ops this SM *can* encode: reload the current value, compute the
+/// result the original op would have stored, and retry a (supported) `AtomOp::CmpExch` until the
+/// value it reads back is the one just read -- the same trick any CAS-only ISA uses to emulate
+/// every other read-modify-write atomic.
+///
+/// Rereading the value at the top of every retry, rather than threading it through a phi from the
+/// previous iteration, costs one redundant load on the common (uncontended, first-try) path, but
+/// sidesteps needing this file to construct SSA phi nodes for a loop carried value -- `sm50.rs`
+/// has never had to do that anywhere else. The arithmetic op this emits (`OpFAdd`/`OpFMnMx`, or
+/// `OpDAdd`/`OpDMnMx` for `F64`) is ordinary, not yet legalized IR; it relies on the shader's
+/// normal `legalize()` walk (which runs `ShaderModel50::legalize_op`, and so `lower_f64_op` for
+/// `OpDAdd` on `emulate_fp64` parts) to legalize it same as any other arithmetic, the same
+/// division of labor `OpMuFu::lower_sm50` already relies on elsewhere in this file. The CAS itself
+/// swaps raw bits, so it uses `U32`/`U64` rather than the float atom type.
+///
+/// Not yet reachable from a real compile: nothing calls this. `OpAtom::legalize` still just runs
+/// `legalize_ext_instr`, and splicing `AtomCasLoop`'s blocks into the CFG needs a pass with
+/// `&mut Function` access that would have to live next to wherever `Shader::legalize()` drives
+/// the per-instruction walk, outside this file. Until that pass exists and calls this, a global
+/// `F64` atomic or shared `F32`/`F64` one still hits `EncodeError::Unsupported`, same as before
+/// this function existed. Exercised today only by `test_lower_unsupported_atom_emits_only_supported_ops`
+/// below.
+#[allow(dead_code)]
+fn lower_unsupported_atom(
+    sm: &ShaderModel50,
+    alloc: &mut SSAValueAllocator,
+    labels: &mut LabelAllocator,
+    atom: &OpAtom,
+) -> AtomCasLoop {
+    let comps: u8 = match atom.atom_type {
+        AtomType::F32 => 1,
+        AtomType::F64 => 2,
+        other => panic!("{other} is not a float atom type, nothing to lower"),
+    };
+    let mem_type = match comps {
+        1 => MemType::B32,
+        2 => MemType::B64,
+        _ => unreachable!(),
+    };
+    let access = MemAccess {
+        mem_type,
+        space: atom.mem_space,
+        order: atom.mem_order,
+        eviction_priority: MemEvictionPriority::Normal,
+    };
+
+    let loop_label = labels.alloc();
+
+    let mut b = SSAInstrBuilder::new(sm, alloc);
+
+    let cur = b.alloc_ssa(RegFile::GPR, comps);
+    b.push_op(OpLd {
+        dst: cur.into(),
+        addr: atom.addr,
+        offset: atom.addr_offset,
+        access,
+    });
+
+    let new_val = b.alloc_ssa(RegFile::GPR, comps);
+    match atom.atom_op {
+        AtomOp::Exch => b.copy_to(new_val.into(), atom.data),
+        AtomOp::Add if comps == 2 => b.push_op(OpDAdd {
+            dst: new_val.into(),
+            srcs: [cur.into(), atom.data],
+            rnd_mode: FRndMode::NearestEven,
+        }),
+        AtomOp::Min | AtomOp::Max if comps == 2 => b.push_op(OpDMnMx {
+            dst: new_val.into(),
+            srcs: [cur.into(), atom.data],
+            min: matches!(atom.atom_op, AtomOp::Min).into(),
+        }),
+        AtomOp::Add => b.push_op(OpFAdd {
+            dst: new_val.into(),
+            srcs: [cur.into(), atom.data],
+            saturate: false,
+            rnd_mode: FRndMode::NearestEven,
+            ftz: false,
+        }),
+        AtomOp::Min | AtomOp::Max => b.push_op(OpFMnMx {
+            dst: new_val.into(),
+            srcs: [cur.into(), atom.data],
+            min: matches!(atom.atom_op, AtomOp::Min).into(),
+            ftz: false,
+        }),
+        other => panic!("{other} has no float semantics to emulate"),
+    };
+
+    let cas_data: SSARef = match comps {
+        1 => SSARef::from([cur[0], new_val[0]]),
+        2 => SSARef::from([cur[0], cur[1], new_val[0], new_val[1]]),
+        _ => unreachable!(),
+    };
+    let old = b.alloc_ssa(RegFile::GPR, comps);
+    b.push_op(OpAtom {
+        dst: old.into(),
+        addr: atom.addr,
+        data: cas_data.into(),
+        addr_offset: atom.addr_offset,
+        atom_op: AtomOp::CmpExch,
+        atom_type: if comps == 2 { AtomType::U64 } else { AtomType::U32 },
+        mem_space: atom.mem_space,
+        mem_order: atom.mem_order,
+    });
+
+    // The CAS only committed our write if the value it read back still matched what we just
+    // read; any mismatch means another lane or thread raced us, so retry with the fresh value.
+    // For the F64 (comps == 2) case this has to compare both words: unlike an ordered compare,
+    // "not equal" doesn't need `lower_i64_isetp`'s borrow-chained `.X` trick (it's unavailable
+    // here anyway -- that helper takes a `LegalizeBuilder`, not this `SSAInstrBuilder`) since
+    // the two words are independent: the pair differs iff either word does, so a plain `Or` of
+    // the per-word compares is exact.
+    let mismatch = match comps {
+        1 => b.isetp(IntCmpType::U32, IntCmpOp::Ne, old.into(), cur.into()),
+        2 => {
+            let lo_ne =
+                b.isetp(IntCmpType::U32, IntCmpOp::Ne, old[0].into(), cur[0].into());
+            let mismatch = b.alloc_ssa(RegFile::Pred, 1);
+            b.push_op(OpISetP {
+                dst: mismatch.into(),
+                set_op: PredSetOp::Or,
+                cmp_op: IntCmpOp::Ne,
+                cmp_type: IntCmpType::U32,
+                srcs: [old[1].into(), cur[1].into()],
+                accum: lo_ne[0].into(),
+                ex: false,
+            });
+            mismatch
+        }
+        _ => unreachable!(),
+    };
+    b.predicate(mismatch[0].into()).push_op(OpBra {
+        target: loop_label,
+    });
+
+    let loop_body = BasicBlock {
+        label: loop_label,
+        uniform: false,
+        instrs: b.as_vec(),
+    };
+
+    let mut post_b = SSAInstrBuilder::new(sm, alloc);
+    post_b.copy_to(atom.dst, old.into());
+    let post = BasicBlock {
+        label: labels.alloc(),
+        uniform: false,
+        instrs: post_b.as_vec(),
+    };
+
+    AtomCasLoop { loop_body, post }
+}
+
 impl SM50Op for OpALd {
     fn legalize(&mut self, b: &mut LegalizeBuilder) {
         legalize_ext_instr(self, b);
@@ -2424,44 +4357,142 @@ impl SM50Op for OpMemBar {
         // Nothing to do
     }
 
-    fn encode(&self, e: &mut SM50Encoder<'_>) {
-        e.set_opcode(0xef98);
+    fn encode(&self, e: &mut SM50Encoder<'_>) {
+        e.set_opcode(0xef98);
+        e.set_field(8..10, membar_scope(self.scope));
+    }
+}
+
+impl SM50Encoder<'_> {
+    /// Records a fixup instead of writing the offset immediately: `self.inst`'s position in the
+    /// final output buffer isn't known yet (only its IP, which `encode_sm50_shader` assigns
+    /// before encoding but doesn't place in the word array until after), so `word_offset` is
+    /// filled in later by `encode_sm50_shader` and the actual bits are written by
+    /// `resolve_relocations` once every instruction has a final position.
+    fn set_rel_offset(&mut self, range: Range<usize>, label: &Label) {
+        self.relocs.push(PendingReloc {
+            word_offset: 0,
+            range,
+            kind: RelocKind::Rel32,
+            label: *label,
+            inst_ip: self.ip,
+        });
+    }
+}
+
+/// `target_ip - inst_ip - 8` plus whether it fits in `bits`, factored out of
+/// `resolve_relocations` so `encode_sm50_shader` can also use it to size-check a candidate
+/// trampoline hop before committing to it.
+fn rel_offset_fits(inst_ip: usize, target_ip: usize, bits: usize) -> (i32, bool) {
+    let value = i32::try_from(target_ip).unwrap() - i32::try_from(inst_ip).unwrap() - 8;
+    let fits = if bits >= 32 {
+        true
+    } else {
+        let min = -(1_i32 << (bits - 1));
+        let max = (1_i32 << (bits - 1)) - 1;
+        value >= min && value <= max
+    };
+    (value, fits)
+}
 
-        e.set_field(
-            8..10,
-            match self.scope {
-                MemScope::CTA => 0_u8,
-                MemScope::GPU => 1_u8,
-                MemScope::System => 2_u8,
-            },
+/// Patches every recorded branch-target fixup into `encoded` now that each instruction's final
+/// word position is known, redirecting a reloc at `trampoline_ip` (rather than erroring out) for
+/// any entry `encode_sm50_shader` already decided needs one. Returns an error naming the
+/// offending branch if a displacement still doesn't fit even after that substitution.
+fn resolve_relocations(
+    encoded: &mut [u32],
+    labels: &HashMap<Label, usize>,
+    trampolines: &HashMap<usize, usize>,
+    relocs: Vec<PendingReloc>,
+) -> Result<(), BranchRangeError> {
+    for reloc in relocs {
+        let target_ip = trampolines.get(&reloc.word_offset).copied().unwrap_or(
+            *labels
+                .get(&reloc.label)
+                .expect("branch target label has no known IP"),
         );
+
+        let bits = reloc.range.len();
+        let (value, fits) = rel_offset_fits(reloc.inst_ip, target_ip, bits);
+        if !fits {
+            return Err(BranchRangeError {
+                inst_ip: reloc.inst_ip,
+                target_ip,
+                bits,
+            });
+        }
+
+        let inst: &mut [u32; 2] = (&mut encoded
+            [reloc.word_offset..reloc.word_offset + 2])
+            .try_into()
+            .unwrap();
+        BitMutView::new(inst).set_field(reloc.range, value);
     }
+    Ok(())
 }
 
-impl SM50Encoder<'_> {
-    fn set_rel_offset(&mut self, range: Range<usize>, label: &Label) {
-        let ip = u32::try_from(self.ip).unwrap();
-        let ip = i32::try_from(ip).unwrap();
+/// Appends a single relay group to `encoded` -- an unconditional `OpBra` (same opcode and field
+/// layout as `OpBra::encode`, via the shared [`OPBRA_OPCODE`]/[`OPBRA_PRED_ALWAYS`] constants) to
+/// `real_target_ip` -- and returns its own ip, computed the same way `encode_sm50_shader`'s main
+/// loop computes every other group's ip. Because it's appended after every real block rather than
+/// spliced into the middle of the instruction stream, adding one never shifts any other label's
+/// ip, so (unlike inserting in place) one pass over `relocs` is enough; no other branch needs
+/// re-checking as a result.
+///
+/// This only ever inserts one hop, so it trades full generality (chaining relays to bridge an
+/// arbitrarily large displacement) for simplicity: it fails with [`BranchRangeError`] when the
+/// relay itself can't reach `real_target_ip`, rather than chaining further relays to bridge that
+/// too. In practice NAK's shaders are nowhere near the ~8 MiB this would take to matter.
+fn append_trampoline(
+    encoded: &mut Vec<u32>,
+    real_target_ip: usize,
+) -> Result<usize, BranchRangeError> {
+    let ip = ((encoded.len() / 2) + 1) * 8;
+
+    let (offset, fits) = rel_offset_fits(ip, real_target_ip, 24);
+    if !fits {
+        return Err(BranchRangeError {
+            inst_ip: ip,
+            target_ip: real_target_ip,
+            bits: 24,
+        });
+    }
 
-        let target_ip = *self.labels.get(label).unwrap();
-        let target_ip = u32::try_from(target_ip).unwrap();
-        let target_ip = i32::try_from(target_ip).unwrap();
+    let mut hop = [0_u32; 2];
+    BitMutView::new(&mut hop).set_field(48..64, OPBRA_OPCODE);
+    BitMutView::new(&mut hop).set_field(0..5, OPBRA_PRED_ALWAYS);
+    BitMutView::new(&mut hop).set_field(20..44, offset);
 
-        let rel_offset = target_ip - ip - 8;
+    let mut nop = [0_u32; 2];
+    BitMutView::new(&mut nop).set_field(48..64, OPNOP_OPCODE);
+    BitMutView::new(&mut nop).set_field(8..12, OPNOP_CC_TRUE);
 
-        self.set_field(range, rel_offset);
-    }
+    encoded.extend_from_slice(&[0, 0]); // sched word for this group
+    encoded.extend_from_slice(&hop);
+    encoded.extend_from_slice(&nop);
+    encoded.extend_from_slice(&nop);
+
+    Ok(ip)
 }
 
+/// Opcode `OpBra::encode` uses, and the one `append_trampoline` hand-builds a relay `BRA` with --
+/// shared so the two can't silently drift apart the way `append_trampoline`'s predicate field
+/// once did.
+const OPBRA_OPCODE: u16 = 0xe240;
+
+/// The `0..5` field value `OpBra::encode` uses for an always-taken branch, and the one
+/// `append_trampoline`'s relay hop needs too, since a trampoline is always unconditional.
+const OPBRA_PRED_ALWAYS: u8 = 0xf;
+
 impl SM50Op for OpBra {
     fn legalize(&mut self, _b: &mut LegalizeBuilder) {
         // Nothing to do
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        e.set_opcode(0xe240);
+        e.set_opcode(OPBRA_OPCODE);
         e.set_rel_offset(20..44, &self.target);
-        e.set_field(0..5, 0xF_u8); // TODO: Pred?
+        e.set_field(0..5, OPBRA_PRED_ALWAYS); // TODO: Pred?
     }
 }
 
@@ -2538,16 +4569,24 @@ impl SM50Op for OpKill {
     }
 }
 
+/// Opcode `OpNop::encode` uses, and the one `append_trampoline` hand-builds a relay `NOP` with --
+/// see [`OPBRA_OPCODE`].
+const OPNOP_OPCODE: u16 = 0x50b0;
+
+/// The `8..12` CC-test field value `OpNop::encode` uses for `CC.T` (always true), and the one
+/// `append_trampoline`'s relay NOPs need too.
+const OPNOP_CC_TRUE: u8 = 0xf;
+
 impl SM50Op for OpNop {
     fn legalize(&mut self, _b: &mut LegalizeBuilder) {
         // Nothing to do
     }
 
     fn encode(&self, e: &mut SM50Encoder<'_>) {
-        e.set_opcode(0x50b0);
+        e.set_opcode(OPNOP_OPCODE);
 
         // TODO: CC flags
-        e.set_field(8..12, 0xf_u8); // CC.T
+        e.set_field(8..12, OPNOP_CC_TRUE); // CC.T
     }
 }
 
@@ -2607,7 +4646,9 @@ impl SM50Op for OpOut {
                 e.set_opcode(0xfbe0);
                 e.set_reg_src(20..28, self.stream);
             }
-            src => panic!("Unsupported src type for OUT: {src}"),
+            // `ShaderModel50::supports` rejects every other `SrcRef` before `encode` is ever
+            // reached.
+            _ => unreachable!("supports() rejects this before encode is called"),
         }
 
         e.set_field(
@@ -2709,16 +4750,18 @@ fn encode_instr(
     labels: &HashMap<Label, usize>,
     ip: &mut usize,
     sched_instr: &mut [u32; 2],
-) -> [u32; 2] {
+) -> Result<([u32; 2], Vec<PendingReloc>), UnsupportedOp> {
     let mut e = SM50Encoder {
         sm,
         ip: *ip,
         labels,
         inst: [0_u32; 2],
         sched: 0,
+        relocs: Vec::new(),
     };
 
     if let Some(instr) = instr {
+        sm.supports(&instr.op)?;
         as_sm50_op(&instr.op).encode(&mut e);
         e.set_pred(&instr.pred);
         e.set_instr_deps(&instr.deps);
@@ -2737,10 +4780,22 @@ fn encode_instr(
     BitMutView::new(sched_instr)
         .set_field(21 * instr_index..21 * (instr_index + 1), e.sched);
 
-    e.inst
+    Ok((e.inst, e.relocs))
 }
 
 fn encode_sm50_shader(sm: &ShaderModel50, s: &Shader<'_>) -> Vec<u32> {
+    try_encode_sm50_shader(sm, s).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// The fallible core of `encode_sm50_shader`: same encoding, but surfaces either an op this SM
+/// can't represent at all ([`UnsupportedOp`], from [`ShaderModel50::supports`]) or a branch
+/// displacement that doesn't fit (even after routing it through a [`append_trampoline`] relay,
+/// [`BranchRangeError`]) as an [`EncodeError`] instead of panicking, so a caller that can lower
+/// the shader a different way (e.g. split it, or pick a different op) gets the chance to.
+fn try_encode_sm50_shader(
+    sm: &ShaderModel50,
+    s: &Shader<'_>,
+) -> Result<Vec<u32>, EncodeError> {
     assert!(s.functions.len() == 1);
     let func = &s.functions[0];
 
@@ -2760,6 +4815,7 @@ fn encode_sm50_shader(sm: &ShaderModel50, s: &Shader<'_>) -> Vec<u32> {
     }
 
     let mut encoded = Vec::new();
+    let mut relocs = Vec::new();
     for b in &func.blocks {
         // A block is composed of groups of 3 instructions.
         let block_num_instrs = b.instrs.len().next_multiple_of(3);
@@ -2771,37 +4827,1032 @@ fn encode_sm50_shader(sm: &ShaderModel50, s: &Shader<'_>) -> Vec<u32> {
 
             let mut sched_instr = [0x0; 2];
 
-            let instr0 = encode_instr(
+            let (instr0, relocs0) = encode_instr(
                 0,
                 instrs_iter.next(),
                 sm,
                 &labels,
                 &mut ip,
                 &mut sched_instr,
-            );
-            let instr1 = encode_instr(
+            )?;
+            let (instr1, relocs1) = encode_instr(
                 1,
                 instrs_iter.next(),
                 sm,
                 &labels,
                 &mut ip,
                 &mut sched_instr,
-            );
-            let instr2 = encode_instr(
+            )?;
+            let (instr2, relocs2) = encode_instr(
                 2,
                 instrs_iter.next(),
                 sm,
                 &labels,
                 &mut ip,
                 &mut sched_instr,
-            );
+            )?;
 
             encoded.extend_from_slice(&sched_instr[..]);
-            encoded.extend_from_slice(&instr0[..]);
-            encoded.extend_from_slice(&instr1[..]);
-            encoded.extend_from_slice(&instr2[..]);
+
+            for (instr, instr_relocs) in
+                [(instr0, relocs0), (instr1, relocs1), (instr2, relocs2)]
+            {
+                let word_offset = encoded.len();
+                encoded.extend_from_slice(&instr[..]);
+                relocs.extend(instr_relocs.into_iter().map(|r| PendingReloc {
+                    word_offset,
+                    ..r
+                }));
+            }
+        }
+    }
+
+    // A branch whose resolved displacement won't fit its field gets a trampoline appended after
+    // all real code instead: a tiny relay block that hops on to the real target, with the
+    // original branch redirected to land on the relay instead. Appending rather than splicing
+    // keeps every label computed above valid, so this never needs a second layout pass.
+    let mut trampolines = HashMap::new();
+    for reloc in &relocs {
+        let target_ip = *labels
+            .get(&reloc.label)
+            .expect("branch target label has no known IP");
+        let (_, fits) = rel_offset_fits(reloc.inst_ip, target_ip, reloc.range.len());
+        if !fits {
+            let trampoline_ip = append_trampoline(&mut encoded, target_ip)?;
+            trampolines.insert(reloc.word_offset, trampoline_ip);
+        }
+    }
+
+    resolve_relocations(&mut encoded, &labels, &trampolines, relocs)?;
+
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod interp {
+    //! A small reference interpreter for the subset of ops `sm50.rs`
+    //! legalizes and encodes. It exists purely to check *semantic*
+    //! equivalence (e.g. that swapping two sources and flipping a compare
+    //! op still computes the same predicate), which bit-pattern-matching
+    //! `encode` output can't catch on its own.
+    use super::*;
+
+    #[derive(Clone)]
+    pub struct SM50Interp {
+        gpr: [u32; 256],
+        pred: [bool; 8],
+        carry: bool,
+        cbuf: HashMap<u32, Vec<u32>>,
+    }
+
+    impl SM50Interp {
+        pub fn new() -> Self {
+            SM50Interp {
+                gpr: [0; 256],
+                pred: [true; 8],
+                carry: false,
+                cbuf: HashMap::new(),
+            }
+        }
+
+        pub fn set_gpr(&mut self, idx: u8, val: u32) {
+            self.gpr[idx as usize] = val;
+        }
+
+        pub fn gpr(&self, idx: u8) -> u32 {
+            self.gpr[idx as usize]
+        }
+
+        pub fn set_pred(&mut self, idx: u8, val: bool) {
+            self.pred[idx as usize] = val;
+        }
+
+        pub fn pred(&self, idx: u8) -> bool {
+            self.pred[idx as usize]
+        }
+
+        fn carry_src(&self, src: &Src) -> bool {
+            match &src.src_ref {
+                SrcRef::Zero => false,
+                SrcRef::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::Carry);
+                    self.carry
+                }
+                src => panic!("SM50Interp: unsupported carry src {src}"),
+            }
+        }
+
+        fn write_carry(&mut self, dst: Dst, val: bool) {
+            match dst {
+                Dst::None => (),
+                Dst::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::Carry);
+                    self.carry = val;
+                }
+                _ => panic!("SM50Interp: unsupported carry dst"),
+            }
+        }
+
+        pub fn set_cbuf(&mut self, binding: u32, offset: u32, val: u32) {
+            self.cbuf
+                .entry(binding)
+                .or_insert_with(|| vec![0; 64])[(offset / 4) as usize] = val;
+        }
+
+        fn cbuf_u32(&self, cb: &CBufRef) -> u32 {
+            let CBuf::Binding(binding) = cb.buf else {
+                panic!("Only CBuf::Binding is supported by the interpreter");
+            };
+            self.cbuf
+                .get(&binding)
+                .map(|words| words[(cb.offset / 4) as usize])
+                .unwrap_or(0)
+        }
+
+        fn raw_u32(&self, src_ref: &SrcRef) -> u32 {
+            match src_ref {
+                SrcRef::Zero => 0,
+                SrcRef::True => 1,
+                SrcRef::False => 0,
+                SrcRef::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::GPR);
+                    self.gpr(reg.base_idx())
+                }
+                SrcRef::Imm32(i) => *i,
+                SrcRef::CBuf(cb) => self.cbuf_u32(cb),
+                src => panic!("SM50Interp: unsupported src ref {src}"),
+            }
+        }
+
+        /// Resolves an integer `Src`, applying `bnot`/`ineg` modifiers.
+        pub fn u32(&self, src: &Src) -> u32 {
+            let raw = self.raw_u32(&src.src_ref);
+            if src.src_mod.is_bnot() {
+                !raw
+            } else if src.src_mod.is_ineg() {
+                (raw as i32).wrapping_neg() as u32
+            } else {
+                raw
+            }
+        }
+
+        /// Resolves a float `Src`, applying `fabs`/`fneg` modifiers.
+        pub fn f32(&self, src: &Src) -> f32 {
+            let mut v = f32::from_bits(self.raw_u32(&src.src_ref));
+            if src.src_mod.has_fabs() {
+                v = v.abs();
+            }
+            if src.src_mod.has_fneg() {
+                v = -v;
+            }
+            v
+        }
+
+        fn reg_pair_u64(&self, reg: RegRef) -> u64 {
+            assert_eq!(reg.file(), RegFile::GPR);
+            assert_eq!(reg.comps(), 2);
+            let lo = self.gpr(reg.base_idx()) as u64;
+            let hi = self.gpr(reg.base_idx() + 1) as u64;
+            lo | (hi << 32)
+        }
+
+        /// Resolves an F64 `Src`, which is always a register pair on SM50.
+        pub fn f64(&self, src: &Src) -> f64 {
+            let bits = match &src.src_ref {
+                SrcRef::Zero => 0,
+                SrcRef::Reg(reg) => self.reg_pair_u64(*reg),
+                src => panic!("SM50Interp: unsupported F64 src {src}"),
+            };
+            let mut v = f64::from_bits(bits);
+            if src.src_mod.has_fabs() {
+                v = v.abs();
+            }
+            if src.src_mod.has_fneg() {
+                v = -v;
+            }
+            v
+        }
+
+        pub fn pred_src(&self, src: &Src) -> bool {
+            let raw = match &src.src_ref {
+                SrcRef::True => true,
+                SrcRef::False => false,
+                SrcRef::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::Pred);
+                    self.pred(reg.base_idx())
+                }
+                src => panic!("SM50Interp: unsupported pred src {src}"),
+            };
+            raw ^ src.src_mod.is_bnot()
+        }
+
+        fn write_u32(&mut self, dst: Dst, val: u32) {
+            match dst {
+                Dst::None => (),
+                Dst::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::GPR);
+                    self.set_gpr(reg.base_idx(), val);
+                }
+                _ => panic!("SM50Interp: unsupported GPR dst"),
+            }
+        }
+
+        fn write_f32(&mut self, dst: Dst, val: f32) {
+            self.write_u32(dst, val.to_bits());
+        }
+
+        fn write_f64(&mut self, dst: Dst, val: f64) {
+            match dst {
+                Dst::None => (),
+                Dst::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::GPR);
+                    assert_eq!(reg.comps(), 2);
+                    let bits = val.to_bits();
+                    self.set_gpr(reg.base_idx(), bits as u32);
+                    self.set_gpr(reg.base_idx() + 1, (bits >> 32) as u32);
+                }
+                _ => panic!("SM50Interp: unsupported F64 dst"),
+            }
+        }
+
+        fn write_pred(&mut self, dst: Dst, val: bool) {
+            match dst {
+                Dst::None => (),
+                Dst::Reg(reg) => {
+                    assert_eq!(reg.file(), RegFile::Pred);
+                    self.set_pred(reg.base_idx(), val);
+                }
+                _ => panic!("SM50Interp: unsupported pred dst"),
+            }
+        }
+
+        fn combine(&self, set_op: PredSetOp, a: bool, b: bool) -> bool {
+            match set_op {
+                PredSetOp::And => a && b,
+                PredSetOp::Or => a || b,
+                PredSetOp::Xor => a ^ b,
+            }
+        }
+
+        fn int_cmp(&self, op: IntCmpOp, signed: bool, a: u32, b: u32) -> bool {
+            if signed {
+                let (a, b) = (a as i32, b as i32);
+                match op {
+                    IntCmpOp::Lt => a < b,
+                    IntCmpOp::Le => a <= b,
+                    IntCmpOp::Gt => a > b,
+                    IntCmpOp::Ge => a >= b,
+                    IntCmpOp::Eq => a == b,
+                    IntCmpOp::Ne => a != b,
+                }
+            } else {
+                match op {
+                    IntCmpOp::Lt => a < b,
+                    IntCmpOp::Le => a <= b,
+                    IntCmpOp::Gt => a > b,
+                    IntCmpOp::Ge => a >= b,
+                    IntCmpOp::Eq => a == b,
+                    IntCmpOp::Ne => a != b,
+                }
+            }
+        }
+
+        fn float_cmp<T: PartialOrd>(&self, op: FloatCmpOp, a: T, b: T) -> bool {
+            match op {
+                FloatCmpOp::OrdLt => a < b,
+                FloatCmpOp::OrdEq => a == b,
+                FloatCmpOp::OrdLe => a <= b,
+                FloatCmpOp::OrdGt => a > b,
+                FloatCmpOp::OrdNe => a != b,
+                FloatCmpOp::OrdGe => a >= b,
+                FloatCmpOp::UnordLt => !(a >= b),
+                FloatCmpOp::UnordEq => !(a != b),
+                FloatCmpOp::UnordLe => !(a > b),
+                FloatCmpOp::UnordGt => !(a <= b),
+                FloatCmpOp::UnordNe => !(a == b),
+                FloatCmpOp::UnordGe => !(a < b),
+            }
+        }
+
+        /// Evaluates a single op, reading sources and writing the
+        /// destination from/to this interpreter's register state. Panics
+        /// on any op this reference interpreter doesn't model yet.
+        pub fn eval(&mut self, op: &Op) {
+            match op {
+                Op::FAdd(i) => {
+                    let v = self.f32(&i.srcs[0]) + self.f32(&i.srcs[1]);
+                    self.write_f32(i.dst, v);
+                }
+                Op::FMul(i) => {
+                    let v = self.f32(&i.srcs[0]) * self.f32(&i.srcs[1]);
+                    self.write_f32(i.dst, v);
+                }
+                Op::FFma(i) => {
+                    let v = self.f32(&i.srcs[0]).mul_add(
+                        self.f32(&i.srcs[1]),
+                        self.f32(&i.srcs[2]),
+                    );
+                    self.write_f32(i.dst, v);
+                }
+                Op::FMnMx(i) => {
+                    let (a, b) = (self.f32(&i.srcs[0]), self.f32(&i.srcs[1]));
+                    let min = self.pred_src(&i.min);
+                    self.write_f32(i.dst, if min { a.min(b) } else { a.max(b) });
+                }
+                Op::FSetP(i) => {
+                    let cmp = self.float_cmp(i.cmp_op, self.f32(&i.srcs[0]), self.f32(&i.srcs[1]));
+                    let accum = self.pred_src(&i.accum);
+                    self.write_pred(i.dst, self.combine(i.set_op, cmp, accum));
+                }
+                Op::DAdd(i) => {
+                    let v = self.f64(&i.srcs[0]) + self.f64(&i.srcs[1]);
+                    self.write_f64(i.dst, v);
+                }
+                Op::DMul(i) => {
+                    let v = self.f64(&i.srcs[0]) * self.f64(&i.srcs[1]);
+                    self.write_f64(i.dst, v);
+                }
+                Op::DFma(i) => {
+                    let v = self.f64(&i.srcs[0]).mul_add(
+                        self.f64(&i.srcs[1]),
+                        self.f64(&i.srcs[2]),
+                    );
+                    self.write_f64(i.dst, v);
+                }
+                Op::DSetP(i) => {
+                    let cmp = self.float_cmp(i.cmp_op, self.f64(&i.srcs[0]), self.f64(&i.srcs[1]));
+                    let accum = self.pred_src(&i.accum);
+                    self.write_pred(i.dst, self.combine(i.set_op, cmp, accum));
+                }
+                Op::IAdd2(i) => {
+                    let carry_in = self.carry_src(&i.carry_in) as u64;
+                    let sum = self.u32(&i.srcs[0]) as u64
+                        + self.u32(&i.srcs[1]) as u64
+                        + carry_in;
+                    self.write_u32(i.dst, sum as u32);
+                    self.write_carry(i.carry_out, sum > u32::MAX as u64);
+                }
+                Op::ISetP(i) => {
+                    let cmp_type_signed = matches!(i.cmp_type, IntCmpType::I32);
+                    let cmp = if i.ex {
+                        // `.X`: the low-half carry/predicate folded into
+                        // `accum` instead of a plain accumulator operand.
+                        self.int_cmp(
+                            i.cmp_op,
+                            cmp_type_signed,
+                            self.u32(&i.srcs[0]),
+                            self.u32(&i.srcs[1]),
+                        ) && self.pred_src(&i.accum)
+                    } else {
+                        self.combine(
+                            i.set_op,
+                            self.int_cmp(
+                                i.cmp_op,
+                                cmp_type_signed,
+                                self.u32(&i.srcs[0]),
+                                self.u32(&i.srcs[1]),
+                            ),
+                            self.pred_src(&i.accum),
+                        )
+                    };
+                    self.write_pred(i.dst, cmp);
+                }
+                Op::Sel(i) => {
+                    let cond = self.pred_src(&i.cond);
+                    let v = if cond {
+                        self.u32(&i.srcs[0])
+                    } else {
+                        self.u32(&i.srcs[1])
+                    };
+                    self.write_u32(i.dst, v);
+                }
+                Op::Lop2(i) => {
+                    let (a, b) = (self.u32(&i.srcs[0]), self.u32(&i.srcs[1]));
+                    let v = match i.op {
+                        LogicOp2::And => a & b,
+                        LogicOp2::Or => a | b,
+                        LogicOp2::Xor => a ^ b,
+                        LogicOp2::PassB => b,
+                    };
+                    self.write_u32(i.dst, v);
+                }
+                op => panic!("SM50Interp: unsupported op {op:?}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interp::SM50Interp;
+    use super::*;
+
+    /// A tiny xorshift PRNG. This isn't used anywhere security-sensitive,
+    /// just to generate a spread of reproducible test inputs without
+    /// pulling in a dependency only this test module needs.
+    struct Rng(u32);
+
+    impl Rng {
+        fn new(seed: u32) -> Self {
+            Rng(seed | 1)
+        }
+
+        fn next_u32(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn next_f32(&mut self) -> f32 {
+            // Small finite values only: the `% 254` term lands in bits 19..26,
+            // so the exponent field stays at 15 or below and NaN/Inf never
+            // show up here, since the `PartialOrd`
+            // based reference comparisons below aren't meant to model
+            // unordered semantics for every cmp_op.
+            let bits = (self.next_u32() & 0x8007_ffff) | ((self.next_u32() % 254) << 19);
+            f32::from_bits(bits)
+        }
+    }
+
+    const CB_BINDING: u32 = 0;
+    const CB_OFFSET: u32 = 0x20;
+
+    fn cbuf_src() -> Src {
+        Src::from(SrcRef::CBuf(CBufRef {
+            buf: CBuf::Binding(CB_BINDING),
+            offset: CB_OFFSET,
+        }))
+    }
+
+    /// `swap_srcs_if_not_reg` plus a `.flip()` of the compare op is the
+    /// exact transform `OpFSetP`/`OpISetP`/`OpDSetP::legalize` apply when
+    /// `srcs[0]` isn't a register. Exercising it directly here (rather
+    /// than by constructing a full `LegalizeBuilder`) still catches the
+    /// regression class the hardware bit-pattern tests can't: a
+    /// `swap_srcs_if_not_reg` call that forgets the matching `.flip()`.
+    #[test]
+    fn test_fsetp_swap_preserves_semantics() {
+        let mut rng = Rng::new(0x5ca1ab1e);
+        for _ in 0..256 {
+            let mut interp = SM50Interp::new();
+            let a = rng.next_f32();
+            let bits = rng.next_u32();
+            interp.set_cbuf(CB_BINDING, CB_OFFSET, bits);
+            let b = f32::from_bits(bits);
+
+            let mut setp = OpFSetP {
+                dst: RegRef::new(RegFile::Pred, 0, 1).into(),
+                set_op: PredSetOp::And,
+                cmp_op: FloatCmpOp::OrdLt,
+                srcs: [cbuf_src(), RegRef::new(RegFile::GPR, 1, 1).into()],
+                accum: true.into(),
+                ftz: false,
+            };
+            interp.set_gpr(1, a.to_bits());
+
+            let mut before = interp.clone();
+            before.eval(&Op::FSetP(setp.clone()));
+
+            let [src0, src1] = &mut setp.srcs;
+            if swap_srcs_if_not_reg(src0, src1, RegFile::GPR) {
+                setp.cmp_op = setp.cmp_op.flip();
+            }
+            let mut after = interp.clone();
+            after.eval(&Op::FSetP(setp));
+
+            assert_eq!(
+                before.pred(0),
+                after.pred(0),
+                "a={a} b={b}: swap_srcs_if_not_reg + flip() changed FSetP semantics"
+            );
+        }
+    }
+
+    #[test]
+    fn test_isetp_swap_preserves_semantics() {
+        let mut rng = Rng::new(0xdeadbeef);
+        for _ in 0..256 {
+            let mut interp = SM50Interp::new();
+            let a = rng.next_u32();
+            let b = rng.next_u32();
+            interp.set_cbuf(CB_BINDING, CB_OFFSET, b);
+            interp.set_gpr(1, a);
+
+            let mut setp = OpISetP {
+                dst: RegRef::new(RegFile::Pred, 0, 1).into(),
+                set_op: PredSetOp::And,
+                cmp_op: IntCmpOp::Lt,
+                cmp_type: IntCmpType::U32,
+                srcs: [cbuf_src(), RegRef::new(RegFile::GPR, 1, 1).into()],
+                accum: true.into(),
+                ex: false,
+            };
+
+            let mut before = interp.clone();
+            before.eval(&Op::ISetP(setp.clone()));
+
+            let [src0, src1] = &mut setp.srcs;
+            if swap_srcs_if_not_reg(src0, src1, RegFile::GPR) {
+                setp.cmp_op = setp.cmp_op.flip();
+            }
+            let mut after = interp.clone();
+            after.eval(&Op::ISetP(setp));
+
+            assert_eq!(
+                before.pred(0),
+                after.pred(0),
+                "a={a} b={b}: swap_srcs_if_not_reg + flip() changed ISetP semantics"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sel_swap_preserves_semantics() {
+        let mut rng = Rng::new(0x1337);
+        for _ in 0..256 {
+            let mut interp = SM50Interp::new();
+            let a = rng.next_u32();
+            let b = rng.next_u32();
+            interp.set_cbuf(CB_BINDING, CB_OFFSET, b);
+            interp.set_gpr(1, a);
+            interp.set_pred(2, rng.next_u32() & 1 == 0);
+
+            let mut sel = OpSel {
+                dst: RegRef::new(RegFile::GPR, 0, 1).into(),
+                srcs: [cbuf_src(), RegRef::new(RegFile::GPR, 1, 1).into()],
+                cond: RegRef::new(RegFile::Pred, 2, 1).into(),
+            };
+
+            let mut before = interp.clone();
+            before.eval(&Op::Sel(sel.clone()));
+
+            let [src0, src1] = &mut sel.srcs;
+            if swap_srcs_if_not_reg(src0, src1, RegFile::GPR) {
+                sel.cond = sel.cond.bnot();
+            }
+            let mut after = interp.clone();
+            after.eval(&Op::Sel(sel));
+
+            assert_eq!(
+                before.gpr(0),
+                after.gpr(0),
+                "a={a:#x} b={b:#x}: swap_srcs_if_not_reg + bnot() changed SEL semantics"
+            );
+        }
+    }
+
+    /// `OpFFma::encode` folds `srcs[0].fneg ^ srcs[1].fneg` into a single
+    /// bit rather than negating a source outright; this checks the
+    /// interpreter (and thus the same XOR identity `encode` relies on)
+    /// agrees with negating either factor directly.
+    #[test]
+    fn test_ffma_fneg_xor_parity() {
+        let mut rng = Rng::new(0xf00d);
+        for _ in 0..256 {
+            let a = rng.next_f32();
+            let b = rng.next_f32();
+            let c = rng.next_f32();
+
+            let mut interp = SM50Interp::new();
+            interp.set_gpr(0, a.to_bits());
+            interp.set_gpr(1, b.to_bits());
+            interp.set_gpr(2, c.to_bits());
+
+            let src = |reg, neg: bool| {
+                let s = Src::from(RegRef::new(RegFile::GPR, reg, 1));
+                if neg {
+                    s.fneg()
+                } else {
+                    s
+                }
+            };
+
+            let eval = |srcs_neg: [bool; 2]| {
+                let mut interp = interp.clone();
+                let ffma = OpFFma {
+                    dst: RegRef::new(RegFile::GPR, 3, 1).into(),
+                    srcs: [
+                        src(0, srcs_neg[0]),
+                        src(1, srcs_neg[1]),
+                        RegRef::new(RegFile::GPR, 2, 1).into(),
+                    ],
+                    rnd_mode: FRndMode::NearestEven,
+                    saturate: false,
+                    ftz: false,
+                    dnz: false,
+                };
+                interp.eval(&Op::FFma(ffma));
+                interp.gpr(3)
+            };
+
+            // Negating exactly one of the two multiplicands and negating
+            // neither-but-XOR-true must land on the same bit pattern as
+            // the encoder's single XOR'd negate bit does. Compare against
+            // `mul_add` (not a separate mul + add) on both sides so this
+            // isn't just checking for double-rounding differences.
+            let neither = eval([false, false]);
+            let xor_true_a = eval([true, false]);
+            let xor_true_b = eval([false, true]);
+            let both = eval([true, true]);
+            let negated_product = (-a).mul_add(b, c);
+
+            assert_eq!(
+                f32::from_bits(xor_true_a),
+                negated_product,
+                "a={a} b={b} c={c}: negating src0 alone didn't negate the product"
+            );
+            assert_eq!(
+                f32::from_bits(xor_true_b),
+                negated_product,
+                "a={a} b={b} c={c}: negating src1 alone didn't negate the product"
+            );
+            assert_eq!(
+                f32::from_bits(both),
+                f32::from_bits(neither),
+                "a={a} b={b} c={c}: negating both srcs (fneg XOR false) should cancel out"
+            );
+        }
+    }
+
+    /// Mirrors `unpack_f64`'s sign/exponent/mantissa split, including the
+    /// implicit leading one that's only folded in when `exp != 0`.
+    /// `LegalizeBuilder` can't be constructed outside a full
+    /// `Shader::legalize()` pass in this crate, so `unpack_f64` itself can't
+    /// be driven directly from a unit test; this reimplements the same bit
+    /// formulas in plain host arithmetic so the algorithm can still be
+    /// checked.
+    fn unpack_f64_host(bits: u64) -> (u32, u32, u64) {
+        let sign = (bits >> 63) as u32;
+        let exp = ((bits >> 52) & 0x7ff) as u32;
+        let frac = bits & 0x000f_ffff_ffff_ffff;
+        let mant = if exp == 0 { frac } else { frac | (1u64 << 52) };
+        (sign, exp, mant)
+    }
+
+    fn pack_f64_host(sign: u32, exp: u32, mant: u64) -> f64 {
+        let bits = ((sign as u64) << 63)
+            | ((exp as u64 & 0x7ff) << 52)
+            | (mant & 0x000f_ffff_ffff_ffff);
+        f64::from_bits(bits)
+    }
+
+    /// Mirrors `normalize_and_round_f64`'s leading-one search and
+    /// round-to-nearest fold, as a signed host shift (positive = right,
+    /// negative = left) rather than `OpShf`'s funnel-shift encoding of it.
+    fn normalize_and_round_f64_host(exp: u32, mant: u64) -> (u32, u64) {
+        if mant == 0 {
+            return (0, 0);
+        }
+        let lz = mant.leading_zeros() as i32;
+        // Leading one is currently at bit `63 - lz`; it belongs at bit 52.
+        let norm_shift = (63 - lz) - 52;
+        let shifted = if norm_shift >= 0 {
+            mant >> norm_shift
+        } else {
+            mant << -norm_shift
+        };
+        let round_bit = if norm_shift >= 1 {
+            (mant >> (norm_shift - 1)) & 1
+        } else {
+            0
+        };
+        let new_exp = (exp as i64 + norm_shift as i64) as u32;
+        (new_exp, shifted.wrapping_add(round_bit))
+    }
+
+    /// Mirrors `lower_f64_add`'s align/negate/sum/renormalize pipeline,
+    /// including the exponent-difference clamp fixed above (an unclamped
+    /// `shift` of 64 or more would otherwise read back whatever bits
+    /// `OpShf`'s modulo-64 wraparound happened to land on instead of zero).
+    fn lower_f64_add_host(a: f64, b: f64) -> f64 {
+        let (sign0, exp0, mant0) = unpack_f64_host(a.to_bits());
+        let (sign1, exp1, mant1) = unpack_f64_host(b.to_bits());
+
+        let (big_exp, small_exp, big_sign, small_sign, big_mant, small_mant) = if exp0 >= exp1 {
+            (exp0, exp1, sign0, sign1, mant0, mant1)
+        } else {
+            (exp1, exp0, sign1, sign0, mant1, mant0)
+        };
+
+        let shift = big_exp - small_exp;
+        let aligned_mant: u64 = if shift >= 64 {
+            0
+        } else {
+            small_mant >> shift.min(63)
+        };
+
+        let addend = if big_sign != small_sign {
+            aligned_mant.wrapping_neg()
+        } else {
+            aligned_mant
+        };
+
+        let sum = big_mant.wrapping_add(addend);
+        let (sum, result_sign) = if (sum as i64) < 0 {
+            (sum.wrapping_neg(), big_sign ^ 1)
+        } else {
+            (sum, big_sign)
+        };
+
+        let (exp, mant) = normalize_and_round_f64_host(big_exp, sum);
+        pack_f64_host(result_sign, exp, mant)
+    }
+
+    /// Targets the exponent-difference clamp added to `lower_f64_add`: an
+    /// 11-bit exponent difference of 64 or more (e.g. `1.0 + 1e-300`) has
+    /// shifted the small operand's mantissa entirely out, so it must read
+    /// as zero rather than whatever `OpShf`'s modulo-64 wrap would produce.
+    #[test]
+    fn test_lower_f64_add_shift_clamp_zeroes_fully_shifted_out_operand() {
+        let mant: u64 = 0x1f_ffff_ffff_ffff;
+        for shift in [0u32, 1, 63, 64, 65, 2000] {
+            let shift_clamped = shift.min(63);
+            let aligned = if shift >= 64 { 0 } else { mant >> shift_clamped };
+            if shift >= 64 {
+                assert_eq!(aligned, 0, "shift={shift}: operand should be fully shifted out");
+            } else {
+                assert_eq!(aligned, mant >> shift, "shift={shift}: clamp changed an in-range shift");
+            }
+        }
+    }
+
+    /// Targets the `exp == 0` special case added to `unpack_f64`: zero and
+    /// subnormal operands have no implicit leading one, so folding one in
+    /// unconditionally would corrupt them by effectively adding 2^52.
+    #[test]
+    fn test_unpack_f64_implicit_bit_only_when_exp_nonzero() {
+        let cases: [(f64, bool); 5] = [
+            (0.0, false),
+            (-0.0, false),
+            (5e-324, false),  // smallest subnormal
+            (2.2250738585072014e-308, true), // smallest normal
+            (1.0, true),
+        ];
+        for (v, expect_implicit) in cases {
+            let (_, exp, mant) = unpack_f64_host(v.to_bits());
+            assert_eq!(
+                mant & (1 << 52) != 0,
+                expect_implicit,
+                "v={v}: implicit leading-one bit set incorrectly for exp={exp}"
+            );
+        }
+    }
+
+    /// Full round-trip of `lower_f64_add`'s algorithm (via the host mirror
+    /// above) against real `f64` addition, covering zero operands, a
+    /// same-magnitude sign mismatch (exact cancellation), a partial-overlap
+    /// sign mismatch, and exponent differences of 64 or more in both
+    /// directions (the case the clamp fix above targets).
+    #[test]
+    fn test_lower_f64_add_matches_hardware_add() {
+        let cases: &[(f64, f64)] = &[
+            (0.0, 0.0),
+            (0.0, 5.0),
+            (-0.0, 5.0),
+            (5.0, -5.0),
+            (1.0, -2.0),
+            (1.0, 1e-300),
+            (1e-300, 1.0),
+            (1.0, -1e-300),
+            (123456.789, 0.0001234),
+            (-7.5, 2.25),
+        ];
+        for &(a, b) in cases {
+            let got = lower_f64_add_host(a, b);
+            let want = a + b;
+            assert_eq!(
+                got.to_bits(),
+                want.to_bits(),
+                "lower_f64_add_host({a}, {b}) = {got} ({:#x}), want {want} ({:#x})",
+                got.to_bits(),
+                want.to_bits(),
+            );
+        }
+    }
+
+    /// Mirrors `lower_f64_mul`'s `exp0 + exp1 - 1023` bias correction:
+    /// adding two already-biased 11-bit exponents double-counts the bias
+    /// once, so it has to be subtracted back out.
+    #[test]
+    fn test_lower_f64_mul_exp_bias_correction() {
+        for (exp0, exp1) in [(1023u32, 1023u32), (1000, 1050), (1, 2046)] {
+            let corrected = exp0.wrapping_add(exp1).wrapping_sub(1023);
+            let want = (exp0 as i64 - 1023) + (exp1 as i64 - 1023) + 1023;
+            assert_eq!(corrected as i64, want, "exp0={exp0} exp1={exp1}");
+        }
+    }
+
+    /// Mirrors `lower_f64_setp`'s sign-magnitude-to-monotonic key transform
+    /// (flip all bits when negative, otherwise force the sign bit to 1) and
+    /// checks it reproduces IEEE-754 total order as an unsigned 64-bit
+    /// compare, across positive, negative, zero, and subnormal values.
+    #[test]
+    fn test_lower_f64_setp_key_transform_preserves_total_order() {
+        fn key(bits: u64) -> u64 {
+            if (bits >> 63) & 1 == 1 {
+                !bits
+            } else {
+                bits | (1 << 63)
+            }
+        }
+
+        let values: [f64; 8] = [-5e-324, -1.5, -0.0, 0.0, 5e-324, 1.0, 2.0, 1e300];
+        for &a in &values {
+            for &b in &values {
+                let want = a < b;
+                let got = key(a.to_bits()) < key(b.to_bits());
+                assert_eq!(got, want, "a={a} b={b}");
+            }
+        }
+    }
+
+    /// Encodes `op` the same way `encode_sm50_shader` does for a real instruction (minus the
+    /// surrounding schedule word and relocations, which only matter for branches), for use by the
+    /// decode round-trip tests below.
+    fn encode_op_for_test(op: &Op) -> [u32; 2] {
+        let sm = ShaderModel50::new(50);
+        let labels = HashMap::new();
+        let mut e = SM50Encoder {
+            sm: &sm,
+            ip: 0,
+            labels: &labels,
+            inst: [0_u32; 2],
+            sched: 0,
+            relocs: Vec::new(),
+        };
+        as_sm50_op(op).encode(&mut e);
+        e.inst
+    }
+
+    fn reg(idx: u8) -> Src {
+        Src::from(RegRef::new(RegFile::GPR, idx, 1))
+    }
+
+    fn dst(idx: u8) -> Dst {
+        Dst::Reg(RegRef::new(RegFile::GPR, idx, 1))
+    }
+
+    /// Every `OpMov` source form (`.reg`, `.imm`, `.cbuf`) round-tripped through
+    /// `encode`/`decode_sm50_instr`. This is exactly the field-overlap risk called out for
+    /// `set_src_imm_i20` vs `set_src_imm32`: MOV's immediate form reuses the same 32-bit field
+    /// `IADD2`'s fast-immediate form uses, so a decoder that dispatched on the wrong opcode or
+    /// picked the wrong bit range would silently read back a different value.
+    #[test]
+    fn test_decode_round_trip_mov() {
+        let cases = [
+            OpMov {
+                dst: RegRef::new(RegFile::GPR, 4, 1).into(),
+                src: reg(7),
+                quad_lanes: 0xf,
+            },
+            OpMov {
+                dst: RegRef::new(RegFile::GPR, 4, 1).into(),
+                src: Src::from(SrcRef::Imm32(0xdead_beef)),
+                quad_lanes: 0x3,
+            },
+            OpMov {
+                dst: RegRef::new(RegFile::GPR, 4, 1).into(),
+                src: cbuf_src(),
+                quad_lanes: 0xf,
+            },
+        ];
+
+        for mov in cases {
+            let inst = encode_op_for_test(&Op::Mov(mov.clone()));
+            let Op::Mov(decoded) = decode_sm50_instr(&inst) else {
+                panic!("decode_sm50_instr didn't recognize a re-encoded MOV");
+            };
+            assert_eq!(decoded.dst, mov.dst);
+            assert_eq!(decoded.src, mov.src);
+            assert_eq!(decoded.quad_lanes, mov.quad_lanes);
+        }
+    }
+
+    /// `OpIAdd2`'s fast-immediate form (`0x1c00`) packs a full 32-bit immediate into the same
+    /// `20..52` range MOV's immediate form uses, so this exercises the other side of that shared
+    /// layout plus the slower `.reg`/`.imm`/`.cbuf` triple and their carry in/out bits.
+    #[test]
+    fn test_decode_round_trip_iadd2() {
+        let cases = [
+            OpIAdd2 {
+                dst: RegRef::new(RegFile::GPR, 5, 1).into(),
+                srcs: [reg(1), Src::from(SrcRef::Imm32(0x1234_5678))],
+                carry_in: Src::from(SrcRef::Zero),
+                carry_out: Dst::None,
+            },
+            OpIAdd2 {
+                dst: RegRef::new(RegFile::GPR, 5, 1).into(),
+                srcs: [reg(1), reg(2).ineg()],
+                carry_in: Src::from(SrcRef::Reg(RegRef::zero(RegFile::Carry, 1))),
+                carry_out: Dst::Reg(RegRef::zero(RegFile::Carry, 1)),
+            },
+        ];
+
+        for iadd2 in cases {
+            let inst = encode_op_for_test(&Op::IAdd2(iadd2.clone()));
+            let Op::IAdd2(decoded) = decode_sm50_instr(&inst) else {
+                panic!("decode_sm50_instr didn't recognize a re-encoded IADD2");
+            };
+            assert_eq!(decoded.dst, iadd2.dst);
+            assert_eq!(decoded.srcs, iadd2.srcs);
+            assert_eq!(decoded.carry_in, iadd2.carry_in);
+            assert_eq!(decoded.carry_out, iadd2.carry_out);
+        }
+    }
+
+    /// Fuzzes every opcode `decode_sm50_instr` knows about through `encode` -> `decode`, checking
+    /// that the round trip is lossless for random registers/immediates/scopes. This is the
+    /// disassembler's regression net against the dozens of hand-written field ranges in this
+    /// file drifting out of sync between an op's `encode` and its `decode_*` counterpart.
+    #[test]
+    fn test_decode_round_trip_fuzz() {
+        let mut rng = Rng::new(0xc0ffee);
+        let rand_reg = |rng: &mut Rng| (rng.next_u32() % 255) as u8;
+
+        for _ in 0..512 {
+            let op = match rng.next_u32() % 4 {
+                0 => Op::Mov(OpMov {
+                    dst: dst(rand_reg(&mut rng)),
+                    src: Src::from(SrcRef::Imm32(rng.next_u32())),
+                    quad_lanes: (rng.next_u32() & 0xf) as u8,
+                }),
+                1 => Op::IAdd2(OpIAdd2 {
+                    dst: dst(rand_reg(&mut rng)),
+                    srcs: [
+                        reg(rand_reg(&mut rng)),
+                        Src::from(SrcRef::Imm32(rng.next_u32())),
+                    ],
+                    carry_in: Src::from(SrcRef::Zero),
+                    carry_out: Dst::None,
+                }),
+                2 => Op::MemBar(OpMemBar {
+                    scope: match rng.next_u32() % 3 {
+                        0 => MemScope::CTA,
+                        1 => MemScope::GPU,
+                        _ => MemScope::System,
+                    },
+                }),
+                _ => Op::CS2R(OpCS2R {
+                    dst: dst(rand_reg(&mut rng)),
+                    idx: (rng.next_u32() & 0xff) as u8,
+                }),
+            };
+
+            let inst = encode_op_for_test(&op);
+            let decoded = decode_sm50_instr(&inst);
+            assert_eq!(
+                format!("{decoded:?}"),
+                format!("{op:?}"),
+                "encode/decode round trip mismatch for {op:?}"
+            );
         }
     }
 
-    encoded
+    fn every_op_is_supported(sm: &ShaderModel50, block: &BasicBlock) -> bool {
+        block.instrs.iter().all(|instr| sm.supports(&instr.op).is_ok())
+    }
+
+    /// `lower_unsupported_atom` is the only thing standing between
+    /// `ShaderModel50::supports` rejecting a float atom type and `OpAtom::encode` actually
+    /// having a bit pattern for it, so every op it emits (other than the `AtomOp::CmpExch`
+    /// itself, which `supports` accepts outright) had better be one SM50 can already encode --
+    /// otherwise lowering one unsupported op would just produce another.
+    #[test]
+    fn test_lower_unsupported_atom_emits_only_supported_ops() {
+        let sm = ShaderModel50::new(50);
+        let mut alloc = SSAValueAllocator::new();
+        let mut labels = LabelAllocator::new();
+
+        for (mem_space, atom_type, atom_op) in [
+            (MemSpace::Global(MemAddrType::A64), AtomType::F64, AtomOp::Add),
+            (MemSpace::Shared, AtomType::F32, AtomOp::Min),
+            (MemSpace::Shared, AtomType::F64, AtomOp::Max),
+        ] {
+            let atom = OpAtom {
+                dst: dst(0),
+                addr: reg(1),
+                data: reg(2),
+                addr_offset: 0,
+                atom_op,
+                atom_type,
+                mem_space,
+                mem_order: MemOrder::Strong(MemScope::CTA),
+            };
+            assert!(
+                sm.supports(&Op::Atom(atom.clone())).is_err(),
+                "test setup bug: {atom_type} in {mem_space:?} is actually supported"
+            );
+
+            let cas_loop = lower_unsupported_atom(&sm, &mut alloc, &mut labels, &atom);
+            assert!(
+                every_op_is_supported(&sm, &cas_loop.loop_body),
+                "loop_body has an op SM50 still can't encode for {atom_type} in {mem_space:?}"
+            );
+            assert!(
+                every_op_is_supported(&sm, &cas_loop.post),
+                "post has an op SM50 still can't encode for {atom_type} in {mem_space:?}"
+            );
+        }
+    }
 }
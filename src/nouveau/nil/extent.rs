@@ -230,6 +230,61 @@ impl Offset4D {
         offset_B.x *= bytes_per_element;
         offset_B
     }
+
+    #[no_mangle]
+    pub extern "C" fn nil_offset4d_px_to_gob_address(
+        self,
+        tiling: &Tiling,
+        format: Format,
+        sample_layout: SampleLayout,
+    ) -> u64 {
+        self.px_to_gob_address(tiling, format, sample_layout)
+    }
+
+    /// Byte offset of this texel within a block-linear (GOB) surface, for CPU-side upload/detile
+    /// of tiled resources. Only the portion of the address inside one tile-wide column is
+    /// computed here: GOBs are stacked column-major (Y fastest, then Z) within a tile, and tiles
+    /// are themselves always exactly one GOB wide in X, so tiles along X chain linearly onto the
+    /// end of each other. Whole rows/slices of tiles beyond that -- i.e. `y`/`z`/array strides --
+    /// are the caller's job, added in as whole `B_to_GOB`-sized strides on the tile-aligned
+    /// extent; a single texel's offset has no way to know how wide the surface is.
+    pub fn px_to_gob_address(
+        self,
+        tiling: &Tiling,
+        format: Format,
+        sample_layout: SampleLayout,
+    ) -> u64 {
+        let off_B = self.px_to_B(format, sample_layout);
+        let tile_extent_B = tiling.extent_B();
+
+        let gob_height = gob_height(tiling.gob_height_is_8);
+        let gob_count_y = 1u32 << tiling.y_log2;
+        let gob_count_z = 1u32 << tiling.z_log2;
+
+        // A tile is always exactly one GOB wide in X, so `off_B.x % tile_extent_B.width` is
+        // already the X coordinate within a single GOB.
+        let x_in_gob = off_B.x % tile_extent_B.width;
+        let y_in_tile = off_B.y % tile_extent_B.height;
+        let z_in_tile = off_B.z % tile_extent_B.depth;
+
+        let gob_y_in_tile = y_in_tile / gob_height;
+        let gob_z_in_tile = z_in_tile / GOB_DEPTH;
+        let y_in_gob = y_in_tile % gob_height;
+
+        let gob_index_in_tile = gob_z_in_tile * gob_count_y + gob_y_in_tile;
+        let tile_x = off_B.x / tile_extent_B.width;
+        let gobs_per_tile = (gob_count_y * gob_count_z) as u64;
+        let gob_index = tile_x as u64 * gobs_per_tile + gob_index_in_tile as u64;
+
+        // The 64x8 swizzle within one 512B GOB; a "4-row" GOB just leaves the upper half unused.
+        let within_gob = ((x_in_gob % 64) / 32) * 256
+            + ((y_in_gob % 8) / 2) * 64
+            + ((x_in_gob % 32) / 16) * 32
+            + (y_in_gob % 2) * 16
+            + (x_in_gob % 16);
+
+        gob_index * 512 + within_gob as u64
+    }
 }
 
 impl Minify<u32> for Extent4D {